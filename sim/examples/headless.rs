@@ -0,0 +1,48 @@
+//! Runs a scripted fight with no window, printing the final state.
+//!
+//! `cargo run -p fungus_sim --example headless`
+
+use std::path::Path;
+
+use fungus_sim::{load_game_data_from_dir, GameState, Input, MatchSettings, Phase};
+
+fn main() {
+    let assets = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../assets"));
+    let data = load_game_data_from_dir(assets);
+
+    let mut state = GameState::new(MatchSettings::default(), &data).unwrap();
+    for frame in 0..3000 {
+        // Player 1 walks in and attacks, player 2 stands still.
+        let p1 = if frame % 60 < 40 {
+            Input::RIGHT
+        } else {
+            Input::LIGHT
+        };
+        let p2 = 0;
+        state.advance_frame([Input { input_bits: p1 }, Input { input_bits: p2 }], &data);
+
+        if let Phase::MatchOver { winner, .. } = state.phase {
+            match winner {
+                Some(winner) => {
+                    println!("frame {frame}: player {} won {:?}", winner + 1, state.wins)
+                }
+                None => println!("frame {frame}: draw {:?}", state.wins),
+            }
+            return;
+        }
+    }
+
+    println!("round {}, wins {:?}", state.round, state.wins);
+    if let Some(playing) = state.phase.playing_state() {
+        for (i, p) in playing.players.iter().enumerate() {
+            println!(
+                "player {}: loc ({}, {}), health {}, stamina {}",
+                i + 1,
+                p.loc.x.to_f32(),
+                p.loc.y.to_f32(),
+                p.health,
+                p.stamina
+            );
+        }
+    }
+}
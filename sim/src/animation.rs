@@ -0,0 +1,765 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::ControlFlow,
+    path::Path,
+    rc::Rc,
+};
+
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use serde::Deserialize;
+
+use crate::{
+    checksum::stable_hash,
+    command::{check_command, Command},
+    geom::{Aabb, Fixed, Vec2},
+};
+
+#[derive(Deserialize)]
+struct AnimationParams {
+    sprite_sheet: SpriteSheetParams,
+    sprites: Vec<AnnotatedSpriteParams>,
+    looping: bool,
+    #[serde(default)]
+    play_backwards: bool,
+    /// Input that plays this animation, from idle.
+    #[serde(default)]
+    command: Option<Command>,
+    /// Forwards speed while playing, in thousandths of a world unit per
+    /// frame.
+    #[serde(default)]
+    speed: Option<i32>,
+    /// How the hitboxes in this animation have to be guarded.
+    #[serde(default)]
+    height: AttackHeight,
+    #[serde(default)]
+    stance: Stance,
+    /// For air animations, what to play when landing cuts them short.
+    /// Defaults to `landing`.
+    #[serde(default)]
+    landing: Option<String>,
+    /// Stamina spent starting this animation as a move. The move can't be
+    /// used without that much stamina.
+    #[serde(default)]
+    stamina_cost: u32,
+    /// Makes this a throw, grabbing with the grabboxes of its sprites.
+    #[serde(default)]
+    throw: Option<ThrowData>,
+}
+
+/// Where an animation is played, and so where its command can be used.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Stance {
+    #[default]
+    Ground,
+    /// Ends as soon as the player lands.
+    Air,
+}
+
+/// The effect of a hitbox, as in Footsies' attack data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub struct AttackData {
+    pub damage: u32,
+    /// Frames the defender can't act for, when hit.
+    pub hitstun: u32,
+    /// Frames the defender can't act for, when guarding.
+    pub blockstun: u32,
+    /// Frames the defender can't act for, when this breaks their guard.
+    pub guard_break_stun: u32,
+    /// Speed the defender is knocked back at, in thousandths of a world unit
+    /// per frame. Slows down by `KNOCKBACK_FRICTION` every frame.
+    pub pushback: i32,
+    /// Taken off the defender's stamina, whether they guard or not.
+    pub stamina_damage: u32,
+    /// Hitboxes in one animation with the same number connect at most once
+    /// between them, however many frames they're out for. Number them
+    /// differently for a move that hits more than once.
+    #[serde(default)]
+    pub hit: u32,
+}
+
+/// What a throw does once its grabbox catches someone. Throws can't be
+/// guarded, but can't grab anyone in the air or in hitstun or blockstun
+/// either.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct ThrowData {
+    /// Played by the thrower once the grab connects.
+    pub success: String,
+    /// Played by the thrower if the animation ends without grabbing anyone.
+    pub whiff: String,
+    /// Frames after the grab in which the defender can escape by pressing
+    /// throw too.
+    pub tech_window: u32,
+    /// Dealt to the defender unless they escape.
+    pub attack: AttackData,
+}
+
+/// A projectile a sprite fires, as it starts.
+#[derive(Deserialize)]
+struct SpawnParams {
+    /// Name of the projectile's animation, which should loop.
+    projectile: String,
+    /// Where the projectile's center starts, in pixel coords of the sprite
+    /// firing it.
+    offset: [u32; 2],
+    /// Forwards speed, in thousandths of a world unit per frame.
+    speed: i32,
+    /// Frames until the projectile fizzles out, if it hasn't hit anything.
+    lifetime: u32,
+}
+
+/// A projectile a sprite fires, in the firing player's frame of reference.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Spawn {
+    pub projectile: String,
+    pub offset: Vec2,
+    pub speed: Fixed,
+    pub lifetime: u32,
+}
+
+/// Moves a sprite can be cancelled into, by their usual input, and when.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct Cancel {
+    pub on: CancelOn,
+    /// Names of the animations.
+    pub into: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum CancelOn {
+    /// Once the move has hit.
+    Hit,
+    /// Once the move has been guarded.
+    Block,
+    /// Whether or not the move connected.
+    Always,
+}
+
+impl CancelOn {
+    fn allows(self, contact: Option<Contact>) -> bool {
+        match self {
+            CancelOn::Hit => contact == Some(Contact::Hit),
+            CancelOn::Block => contact == Some(Contact::Block),
+            CancelOn::Always => true,
+        }
+    }
+}
+
+/// How an attack connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Contact {
+    Hit,
+    /// Guarded, without breaking the guard.
+    Block,
+}
+
+/// How far into a move its player is. A move is active from its first sprite
+/// that can hit, grab or fire something to its last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MovePhase {
+    Startup,
+    Active,
+    Recovery,
+}
+
+/// Which guards stop an attack.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum AttackHeight {
+    /// Overheads, which have to be guarded standing.
+    High,
+    /// Guarded standing or crouching.
+    #[default]
+    Mid,
+    /// Has to be guarded crouching.
+    Low,
+}
+
+#[derive(Deserialize)]
+struct AnnotatedSpriteParams {
+    hurtbox: bool,
+    /// Pixel coords in the current tile of the spritesheet.
+    /// Top-left is (0,0).
+    hitbox: Option<PixelRect>,
+    /// What the hitbox does to whoever it connects with. Required with a
+    /// hitbox.
+    #[serde(default)]
+    attack: Option<AttackData>,
+    /// Keeps players apart. Defaults to the bounds of the opaque pixels.
+    ///
+    /// Attacking sprites override it to cover just the body, leaving out
+    /// whatever is stretched out to hit (a neck, a leg). Otherwise the
+    /// outstretched part pushes the opponent away on every swing.
+    #[serde(default)]
+    pushbox: Option<PixelRect>,
+    /// Grabs whoever's hurtbox it touches, in a throw animation.
+    #[serde(default)]
+    grabbox: Option<PixelRect>,
+    /// Where this move can be cut short by another, for combos.
+    #[serde(default)]
+    cancels: Vec<Cancel>,
+    /// Fired on the first frame of this sprite.
+    #[serde(default)]
+    spawn: Option<SpawnParams>,
+    duration: usize,
+    sprite_index: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Hash, Deserialize)]
+pub struct PixelRect {
+    pub offset: [u32; 2],
+    pub size: [u32; 2],
+}
+
+#[derive(Deserialize)]
+struct SpriteSheetParams {
+    texture: String,
+    count_x: u32,
+    count_y: u32,
+}
+
+pub type Animations = HashMap<String, Rc<AnimationData>>;
+
+#[derive(Clone)]
+pub struct Animation {
+    data: Rc<AnimationData>,
+    sprite_index: usize,
+    frame_counter: usize,
+    /// Bit `n` is set once a hitbox with `hit: n` has connected, so that
+    /// this instance of the animation can't land it again.
+    hits_landed: u32,
+    /// How the last hitbox to connect did.
+    contact: Option<Contact>,
+}
+
+#[derive(Hash)]
+pub struct AnimationData {
+    /// File stem of the `.ron` this was loaded from.
+    name: String,
+    sprites: Vec<AnnotatedSprite>,
+    looping: bool,
+    pub(crate) command: Option<Command>,
+    speed: Option<Fixed>,
+    height: AttackHeight,
+    pub(crate) stance: Stance,
+    landing: String,
+    pub(crate) stamina_cost: u32,
+    throw: Option<ThrowData>,
+    /// First and last sprites that can hit, grab or fire something, if any
+    /// can.
+    active: Option<(usize, usize)>,
+}
+
+/// Using player frame of refence, world scale.
+#[derive(Hash)]
+pub struct AnnotatedSprite {
+    /// Name of the spritesheet texture, for the renderer.
+    pub texture: String,
+    pub source_rect: PixelRect,
+    pub hitbox: Option<Aabb>,
+    /// Set exactly when `hitbox` is.
+    pub attack: Option<AttackData>,
+    pub hurtbox: Option<Aabb>,
+    pub pushbox: Aabb,
+    pub grabbox: Option<Aabb>,
+    pub cancels: Vec<Cancel>,
+    pub spawn: Option<Spawn>,
+    pub size: Vec2,
+    duration: usize,
+}
+
+/// Loads every `.ron` animation in `files`, using the `.png` spritesheets
+/// (keyed by file stem) from the same set of files.
+///
+/// Takes raw file contents so that callers can use whatever asset source they
+/// have, be it the filesystem or files included into the executable.
+pub fn load_animations<'a>(files: impl IntoIterator<Item = (&'a Path, &'a [u8])>) -> Animations {
+    let mut textures = HashMap::new();
+    let mut params = Vec::new();
+
+    for (path, contents) in files {
+        let Some(ext) = path.extension() else {
+            continue;
+        };
+        let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+        if ext == "png" {
+            let image = image::load_from_memory_with_format(contents, ImageFormat::Png)
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+            textures.insert(name, image);
+        } else if ext == "ron" {
+            let contents = std::str::from_utf8(contents).unwrap();
+            let anim = ron::from_str::<AnimationParams>(contents)
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+            params.push((name, anim));
+        }
+    }
+
+    let anims: Animations = params
+        .into_iter()
+        .map(|(name, anim)| {
+            let data = Rc::new(load_animation(name.clone(), anim, &textures));
+            (name, data)
+        })
+        .collect();
+
+    for data in anims.values() {
+        if data.stance == Stance::Air {
+            assert!(
+                anims.contains_key(&data.landing),
+                "{}: missing landing animation {}",
+                data.name,
+                data.landing
+            );
+        }
+        let cancels = data.sprites.iter().flat_map(|sprite| &sprite.cancels);
+        for name in cancels.flat_map(|cancel| &cancel.into) {
+            assert!(
+                anims.contains_key(name),
+                "{}: missing cancel animation {name}",
+                data.name
+            );
+        }
+        let spawns = data
+            .sprites
+            .iter()
+            .filter_map(|sprite| sprite.spawn.as_ref());
+        for spawn in spawns {
+            assert!(
+                anims.contains_key(&spawn.projectile),
+                "{}: missing projectile animation {}",
+                data.name,
+                spawn.projectile
+            );
+        }
+        if let Some(throw) = &data.throw {
+            // Otherwise being thrown would never end.
+            assert!(
+                throw.tech_window > 0,
+                "{}: throws need a tech window of at least a frame",
+                data.name
+            );
+            for name in [&throw.success, &throw.whiff] {
+                assert!(
+                    anims.contains_key(name),
+                    "{}: missing throw animation {name}",
+                    data.name
+                );
+            }
+        }
+    }
+    anims
+}
+
+/// Animations that are played by a command from `stance`, in the order
+/// they're checked: commands ending in a button press first, then those with
+/// a motion, then by name.
+pub(crate) fn commands(anims: &Animations, stance: Stance) -> Vec<(&Rc<AnimationData>, &Command)> {
+    let mut commands: Vec<_> = anims
+        .values()
+        .filter(|data| data.stance == stance)
+        .filter_map(|data| Some((data, data.command.as_ref()?)))
+        .collect();
+    commands.sort_by_key(|(data, command)| {
+        (
+            command.button.is_none(),
+            command.motion.is_none(),
+            &data.name,
+        )
+    });
+    commands
+}
+
+/// Identifies a set of animation data, so that replays can check they are
+/// being played back with the data they were recorded with.
+pub fn animations_hash(anims: &Animations) -> u64 {
+    let mut all: Vec<&AnimationData> = anims.values().map(|data| &**data).collect();
+    all.sort_by(|a, b| a.name.cmp(&b.name));
+    stable_hash(&all)
+}
+
+impl AnimationData {
+    pub(crate) fn is_named(&self, name: &str) -> bool {
+        self.name == name
+    }
+
+    pub fn to_anim(self: &Rc<Self>) -> Animation {
+        Animation {
+            data: Rc::clone(self),
+            sprite_index: 0,
+            frame_counter: 0,
+            hits_landed: 0,
+            contact: None,
+        }
+    }
+}
+
+impl Animation {
+    pub fn is_instance(&self, data: &Rc<AnimationData>) -> bool {
+        Rc::ptr_eq(&self.data, data)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.data.name
+    }
+
+    /// Forwards speed this animation moves the player at, if it does.
+    pub fn speed(&self) -> Option<Fixed> {
+        self.data.speed
+    }
+
+    pub fn height(&self) -> AttackHeight {
+        self.data.height
+    }
+
+    pub fn stance(&self) -> Stance {
+        self.data.stance
+    }
+
+    /// The animation to play when landing during this one.
+    pub(crate) fn landing(&self) -> &str {
+        &self.data.landing
+    }
+
+    /// Where the current sprite is in the move, unless nothing in this
+    /// animation can hit, grab or fire anything.
+    pub fn phase(&self) -> Option<MovePhase> {
+        let (first, last) = self.data.active?;
+        Some(if self.sprite_index < first {
+            MovePhase::Startup
+        } else if self.sprite_index <= last {
+            MovePhase::Active
+        } else {
+            MovePhase::Recovery
+        })
+    }
+
+    /// What this does if it's a throw.
+    pub fn throw(&self) -> Option<&ThrowData> {
+        self.data.throw.as_ref()
+    }
+}
+
+/// Animations are identified by name rather than by pointer, so that the
+/// hash is the same on every peer.
+impl Hash for Animation {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.name.hash(state);
+        self.sprite_index.hash(state);
+        self.frame_counter.hash(state);
+        self.hits_landed.hash(state);
+        self.contact.hash(state);
+    }
+}
+
+impl fmt::Debug for Animation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Animation")
+            .field("name", &self.data.name)
+            .field("sprite_index", &self.sprite_index)
+            .field("frame_counter", &self.frame_counter)
+            .field("hits_landed", &self.hits_landed)
+            .field("contact", &self.contact)
+            .finish()
+    }
+}
+
+fn load_animation(
+    name: String,
+    anim: AnimationParams,
+    textures: &HashMap<String, DynamicImage>,
+) -> AnimationData {
+    let mut sprites = Vec::with_capacity(anim.sprites.len());
+
+    let mut expected_sprite_index = 0;
+    for params in anim.sprites {
+        let sprite_index = params.sprite_index.unwrap_or(expected_sprite_index);
+        sprites.push(load_sprite(
+            sprite_index,
+            &anim.sprite_sheet,
+            &params,
+            textures,
+        ));
+        expected_sprite_index = sprite_index + 1;
+    }
+
+    if anim.play_backwards {
+        sprites.reverse();
+    }
+    if let Some(command) = &anim.command {
+        check_command(&name, command);
+    }
+    assert!(
+        anim.throw.is_some() || sprites.iter().all(|sprite| sprite.grabbox.is_none()),
+        "{name}: grabboxes are only for throws"
+    );
+    let is_active = |sprite: &AnnotatedSprite| {
+        sprite.hitbox.is_some() || sprite.grabbox.is_some() || sprite.spawn.is_some()
+    };
+    let first_active = sprites.iter().position(is_active);
+    let last_active = sprites.iter().rposition(is_active);
+    AnimationData {
+        name,
+        looping: anim.looping,
+        sprites,
+        command: anim.command,
+        speed: anim.speed.map(|speed| Fixed::from_ratio(speed, 1000)),
+        height: anim.height,
+        stance: anim.stance,
+        landing: anim.landing.unwrap_or_else(|| "landing".to_string()),
+        stamina_cost: anim.stamina_cost,
+        throw: anim.throw,
+        active: first_active.zip(last_active),
+    }
+}
+
+fn load_sprite(
+    sprite_index: usize,
+    sprite_sheet: &SpriteSheetParams,
+    sprite: &AnnotatedSpriteParams,
+    textures: &HashMap<String, DynamicImage>,
+) -> AnnotatedSprite {
+    let image = textures
+        .get(&sprite_sheet.texture)
+        .unwrap_or_else(|| panic!("missing texture {}", sprite_sheet.texture));
+
+    let sprite_width = image.width() / sprite_sheet.count_x;
+    let sprite_height = image.height() / sprite_sheet.count_y;
+
+    let x = sprite_index as u32 % sprite_sheet.count_x;
+    let y = sprite_index as u32 / sprite_sheet.count_x;
+    assert!(y < sprite_sheet.count_y);
+
+    let sprite_x = sprite_width * x;
+    let sprite_y = sprite_height * y;
+
+    let sprite_image =
+        image::imageops::crop_imm(image, sprite_x, sprite_y, sprite_width, sprite_height);
+
+    let mut min_x = sprite_width - 1;
+    let mut max_x = 0; // exclusive
+    for (x, _y, value) in sprite_image.pixels() {
+        if value.0[3] != 0 {
+            min_x = min_x.min(x);
+            max_x = max_x.max(x + 1);
+        }
+    }
+
+    let mut min_y = sprite_height - 1;
+    let mut max_y = 0; // exclusive
+    for (_x, y, value) in sprite_image.pixels() {
+        if value.0[3] != 0 {
+            min_y = min_y.min(y);
+            max_y = max_y.max(y + 1);
+        }
+    }
+
+    // in: pixel, y-down, (0,0) topleft
+    // out: world, y-up, (0,0) center of player
+    //
+    // A tile is scaled to 0.4 world units wide, whatever its pixel size. This
+    // is all integer math, so that every peer gets identical boxes.
+    let (width, height) = (sprite_width as i32, sprite_height as i32);
+    let scale = 5 * width;
+    let to_world = |x: u32, y: u32| Vec2 {
+        x: Fixed::from_ratio(2 * x as i32 - width, scale),
+        y: Fixed::from_ratio(height - 2 * y as i32, scale),
+    };
+
+    let hurtbox = Aabb {
+        min: to_world(min_x, max_y),
+        max: to_world(max_x, min_y),
+    };
+
+    let rect_to_world = |rect: PixelRect| Aabb {
+        min: to_world(rect.offset[0], rect.offset[1] + rect.size[1]),
+        max: to_world(rect.offset[0] + rect.size[0], rect.offset[1]),
+    };
+    let hitbox = sprite.hitbox.map(rect_to_world);
+    if let Some(attack) = &sprite.attack {
+        assert!(
+            attack.hit < u32::BITS,
+            "{}: sprite {sprite_index} hit numbers must be under {}",
+            sprite_sheet.texture,
+            u32::BITS
+        );
+    }
+    assert_eq!(
+        hitbox.is_some(),
+        sprite.attack.is_some(),
+        "{}: sprite {sprite_index} needs attack data exactly when it has a hitbox",
+        sprite_sheet.texture
+    );
+    let pushbox = sprite.pushbox.map_or(hurtbox, rect_to_world);
+    let grabbox = sprite.grabbox.map(rect_to_world);
+    let spawn = sprite.spawn.as_ref().map(|spawn| Spawn {
+        projectile: spawn.projectile.clone(),
+        offset: to_world(spawn.offset[0], spawn.offset[1]),
+        speed: Fixed::from_ratio(spawn.speed, 1000),
+        lifetime: spawn.lifetime,
+    });
+
+    AnnotatedSprite {
+        texture: sprite_sheet.texture.clone(),
+        source_rect: PixelRect {
+            offset: [sprite_x, sprite_y],
+            size: [sprite_width, sprite_height],
+        },
+        hurtbox: sprite.hurtbox.then_some(hurtbox),
+        hitbox,
+        attack: sprite.attack,
+        pushbox,
+        grabbox,
+        cancels: sprite.cancels.clone(),
+        spawn,
+        size: Vec2 {
+            x: Fixed::from_ratio(2 * width, scale),
+            y: Fixed::from_ratio(2 * height, scale),
+        },
+        duration: sprite.duration,
+    }
+}
+
+impl Animation {
+    pub fn next_frame(&mut self) -> ControlFlow<()> {
+        self.frame_counter += 1;
+        if self.data.sprites[self.sprite_index].duration <= self.frame_counter {
+            self.sprite_index += 1;
+            self.frame_counter = 0;
+
+            if self.sprite_index >= self.data.sprites.len() {
+                self.sprite_index = 0;
+                if !self.data.looping {
+                    return ControlFlow::Break(());
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    pub fn sprite(&self) -> &AnnotatedSprite {
+        &self.data.sprites[self.sprite_index]
+    }
+
+    /// The current hitbox's attack, unless it has already connected.
+    pub fn unlanded_attack(&self) -> Option<AttackData> {
+        self.sprite()
+            .attack
+            .filter(|attack| self.hits_landed & (1 << attack.hit) == 0)
+    }
+
+    pub(crate) fn land(&mut self, attack: &AttackData, contact: Contact) {
+        self.hits_landed |= 1 << attack.hit;
+        self.contact = Some(contact);
+    }
+
+    /// The projectile to fire this frame, if the current sprite fires one
+    /// and has just started.
+    pub(crate) fn spawn(&self) -> Option<&Spawn> {
+        self.sprite()
+            .spawn
+            .as_ref()
+            .filter(|_| self.frame_counter == 0)
+    }
+
+    /// Animations the current sprite can be cancelled into right now.
+    pub(crate) fn cancels(&self) -> Vec<String> {
+        self.sprite()
+            .cancels
+            .iter()
+            .filter(|cancel| cancel.on.allows(self.contact))
+            .flat_map(|cancel| cancel.into.iter().cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A frame of a hitbox doing `hit`.
+    fn hitting(hit: u32) -> AnnotatedSprite {
+        let attack = AttackData {
+            damage: 10,
+            hitstun: 30,
+            blockstun: 20,
+            guard_break_stun: 120,
+            pushback: 0,
+            stamina_damage: 0,
+            hit,
+        };
+        let b = Aabb {
+            min: Vec2::ZERO,
+            max: Vec2::new(Fixed::ONE, Fixed::ONE),
+        };
+        AnnotatedSprite {
+            texture: String::new(),
+            source_rect: PixelRect {
+                offset: [0, 0],
+                size: [1, 1],
+            },
+            hitbox: Some(b),
+            attack: Some(attack),
+            hurtbox: None,
+            pushbox: b,
+            grabbox: None,
+            cancels: Vec::new(),
+            spawn: None,
+            size: Vec2::ZERO,
+            duration: 1,
+        }
+    }
+
+    /// Each sprite lasting a frame, doing those `hits`.
+    fn multi_hit(hits: &[u32]) -> Rc<AnimationData> {
+        Rc::new(AnimationData {
+            name: "multi_hit".to_string(),
+            sprites: hits.iter().map(|&hit| hitting(hit)).collect(),
+            looping: false,
+            command: None,
+            speed: None,
+            height: AttackHeight::Mid,
+            stance: Stance::Ground,
+            landing: "landing".to_string(),
+            stamina_cost: 0,
+            throw: None,
+            active: Some((0, hits.len() - 1)),
+        })
+    }
+
+    /// Lands whatever can land on each frame of `anim`, and returns the hit
+    /// numbers that did.
+    fn landed(anim: &mut Animation) -> Vec<u32> {
+        let mut landed = Vec::new();
+        loop {
+            if let Some(attack) = anim.unlanded_attack() {
+                anim.land(&attack, Contact::Hit);
+                landed.push(attack.hit);
+            }
+            if anim.next_frame().is_break() {
+                return landed;
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "tech window")]
+    fn throws_need_a_tech_window() {
+        let assets = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../assets"));
+        let ron = std::fs::read_to_string(assets.join("throw.ron"))
+            .unwrap()
+            .replace("tech_window: 10", "tech_window: 0");
+        let png = std::fs::read(assets.join("attack_0.png")).unwrap();
+        load_animations([
+            (Path::new("throw.ron"), ron.as_bytes()),
+            (Path::new("attack_0.png"), png.as_slice()),
+        ]);
+    }
+
+    #[test]
+    fn each_hit_number_lands_once_per_instance() {
+        let data = multi_hit(&[0, 0, 1, 1, 0, 2]);
+        assert_eq!(landed(&mut data.to_anim()), [0, 1, 2]);
+        // A new instance of the move starts over.
+        assert_eq!(landed(&mut data.to_anim()), [0, 1, 2]);
+    }
+}
@@ -0,0 +1,28 @@
+use crate::data::Rules;
+
+/// The hits a player has taken since they last got back to `Idle`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Combo {
+    pub hits: u32,
+    /// After scaling.
+    pub damage: u32,
+    /// Whether every hit after the first landed while the player was still
+    /// in hitstun, so they never had a chance to get out.
+    pub valid: bool,
+}
+
+impl Combo {
+    /// `damage` for the next hit of the combo, scaled down by the hits
+    /// before it.
+    pub(crate) fn scale(&self, damage: u32, rules: &Rules) -> u32 {
+        let lost = rules.combo_scaling.saturating_mul(self.hits);
+        let percent = 100u32.saturating_sub(lost).max(rules.min_combo_scaling);
+        damage * percent / 100
+    }
+
+    pub(crate) fn add_hit(&mut self, damage: u32, in_hitstun: bool) {
+        self.valid = self.hits == 0 || (self.valid && in_hitstun);
+        self.hits += 1;
+        self.damage += damage;
+    }
+}
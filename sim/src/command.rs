@@ -0,0 +1,429 @@
+//! Recognizing motion inputs (dashes, quarter-circles, charges) in a player's
+//! input history.
+//!
+//! Directions are relative to where the player faces, so that the same
+//! motion works from either side of the screen.
+
+use serde::Deserialize;
+
+use crate::{history::InputHistory, player::Direction, Input, INPUT_HISTORY_LEN};
+
+/// A direction on the stick, relative to the player's facing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum RelDir {
+    Neutral,
+    Forward,
+    Back,
+    Up,
+    Down,
+    UpForward,
+    UpBack,
+    DownForward,
+    DownBack,
+}
+
+impl RelDir {
+    /// `(x, y)`, with forwards and up positive.
+    fn axes(self) -> (i8, i8) {
+        match self {
+            RelDir::Neutral => (0, 0),
+            RelDir::Forward => (1, 0),
+            RelDir::Back => (-1, 0),
+            RelDir::Up => (0, 1),
+            RelDir::Down => (0, -1),
+            RelDir::UpForward => (1, 1),
+            RelDir::UpBack => (-1, 1),
+            RelDir::DownForward => (1, -1),
+            RelDir::DownBack => (-1, -1),
+        }
+    }
+
+    /// Whether `input` holds exactly this direction.
+    pub(crate) fn is_held(self, input: Input, facing: Direction) -> bool {
+        stick_axes(input, facing) == self.axes()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Button {
+    Light,
+    Medium,
+    Heavy,
+    Throw,
+}
+
+impl Button {
+    pub(crate) fn bit(self) -> u8 {
+        match self {
+            Button::Light => Input::LIGHT,
+            Button::Medium => Input::MEDIUM,
+            Button::Heavy => Input::HEAVY,
+            Button::Throw => Input::THROW,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub enum Motion {
+    /// Tap a horizontal direction, let go, and tap it again.
+    DoubleTap(RelDir),
+    /// Down, down and towards, then towards, e.g. `QuarterCircle(Forward)`.
+    QuarterCircle(RelDir),
+    /// Hold `from` for at least `frames`, then move to `to`.
+    Charge {
+        from: RelDir,
+        frames: u32,
+        to: RelDir,
+    },
+}
+
+/// An input sequence, as written alongside the animation it triggers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct Command {
+    /// Without a motion, the button alone is enough.
+    #[serde(default)]
+    pub motion: Option<Motion>,
+    /// Pressed at the end of the motion. Commands without a button fire on
+    /// the frame the motion completes, and aren't buffered.
+    #[serde(default)]
+    pub button: Option<Button>,
+    /// Frames the whole motion has to fit in, before the button press (or
+    /// the final direction, without a button).
+    #[serde(default)]
+    pub window: u32,
+}
+
+impl Command {
+    /// Frames of history needed to recognize this, without any buffering.
+    fn frames_needed(&self) -> usize {
+        let charge = match self.motion {
+            Some(Motion::Charge { frames, .. }) => frames,
+            Some(Motion::DoubleTap(_) | Motion::QuarterCircle(_)) | None => 0,
+        };
+        (self.window + charge) as usize + 1
+    }
+
+    /// Checks the history for this command, and if it's there, marks its
+    /// button press as used.
+    pub(crate) fn take(
+        &self,
+        history: &mut InputHistory,
+        facing: Direction,
+        buffer_frames: u32,
+    ) -> bool {
+        let stick = Stick { history, facing };
+        let Some(button) = self.button else {
+            return self.motion.is_some() && self.motion_ends_at(&stick, 0, true);
+        };
+
+        let end = (0..=buffer_frames as usize).find(|&frames_ago| {
+            history.unused_press(button.bit(), frames_ago)
+                && self.motion_ends_at(&stick, frames_ago, false)
+        });
+        match end {
+            Some(frames_ago) => {
+                history.consume(button.bit(), frames_ago);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the motion was completed within `window` frames before (and
+    /// including) `end` frames ago. `exact_end` requires it to complete on
+    /// exactly that frame, rather than being held there.
+    fn motion_ends_at(&self, stick: &Stick, end: usize, exact_end: bool) -> bool {
+        let Some(motion) = &self.motion else {
+            return true;
+        };
+        let start = end + self.window as usize;
+        match *motion {
+            Motion::DoubleTap(dir) => {
+                let (x, _) = dir.axes();
+                let held = |frames_ago| stick.axes(frames_ago).0 == x;
+                if !held(end) || (exact_end && held(end + 1)) {
+                    return false;
+                }
+                // Let go and tapped before, within the window.
+                let Some(release) = (end + 1..=start).find(|&f| !held(f)) else {
+                    return false;
+                };
+                (release + 1..=start).any(held)
+            }
+            Motion::QuarterCircle(dir) => {
+                let (x, _) = dir.axes();
+                let sequence = [(0, -1), (x, -1), (x, 0)];
+                if exact_end && stick.axes(end + 1) == sequence[2] {
+                    return false;
+                }
+                // Oldest first, skipping anything in between steps.
+                let mut next = 0;
+                for frames_ago in (end..=start).rev() {
+                    if next < sequence.len() && stick.axes(frames_ago) == sequence[next] {
+                        next += 1;
+                    }
+                }
+                next == sequence.len() && stick.axes(end) == sequence[2]
+            }
+            Motion::Charge { from, frames, to } => {
+                if stick.axes(end) != to.axes() || (exact_end && stick.axes(end + 1) == to.axes()) {
+                    return false;
+                }
+                // The charge has to end within the window, and have been held
+                // for `frames` up to then.
+                (end + 1..=start).any(|charged| {
+                    (charged..charged + frames as usize).all(|f| stick.axes(f) == from.axes())
+                })
+            }
+        }
+    }
+}
+
+/// The history, read as stick directions relative to `facing`.
+struct Stick<'a> {
+    history: &'a InputHistory,
+    facing: Direction,
+}
+
+impl Stick<'_> {
+    fn axes(&self, frames_ago: usize) -> (i8, i8) {
+        stick_axes(self.history.get(frames_ago), self.facing)
+    }
+}
+
+fn stick_axes(input: Input, facing: Direction) -> (i8, i8) {
+    let (forward, back) = match facing {
+        Direction::East => (Input::RIGHT, Input::LEFT),
+        Direction::West => (Input::LEFT, Input::RIGHT),
+    };
+    let axis = |positive: u8, negative: u8| {
+        let pressed = |bit| input.input_bits & bit != 0;
+        pressed(positive) as i8 - pressed(negative) as i8
+    };
+    (axis(forward, back), axis(Input::JUMP, Input::DOWN))
+}
+
+/// Panics if `command` can never be recognized, because the history is too
+/// short for it. A buffered press can reach a little further back, where
+/// inputs past the end of the history just read as neutral.
+pub(crate) fn check_command(name: &str, command: &Command) {
+    assert!(
+        command.motion.is_some() || command.button.is_some(),
+        "{name}: command needs a motion, a button, or both"
+    );
+    let needed = command.frames_needed();
+    assert!(
+        needed <= INPUT_HISTORY_LEN,
+        "{name}: command needs {needed} frames of history, only {INPUT_HISTORY_LEN} are kept"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N: u8 = 0;
+    const F: u8 = Input::RIGHT;
+    const B: u8 = Input::LEFT;
+    const D: u8 = Input::DOWN;
+    const DF: u8 = Input::DOWN | Input::RIGHT;
+    const L: u8 = Input::LIGHT;
+
+    /// Inputs written facing east, each held for a number of frames, oldest
+    /// first.
+    fn frames(parts: &[(u8, usize)]) -> Vec<u8> {
+        parts
+            .iter()
+            .flat_map(|&(bits, count)| std::iter::repeat_n(bits, count))
+            .collect()
+    }
+
+    fn mirror(bits: u8) -> u8 {
+        let swapped = if bits & F != 0 { B } else { 0 } | if bits & B != 0 { F } else { 0 };
+        bits & !(F | B) | swapped
+    }
+
+    fn take(command: &Command, inputs: &[u8], facing: Direction, buffer_frames: u32) -> bool {
+        let mut history = InputHistory::default();
+        for &input_bits in inputs {
+            history.push(Input { input_bits });
+        }
+        command.take(&mut history, facing, buffer_frames)
+    }
+
+    /// Whether `command` is recognized at the end of `inputs`, checking that
+    /// it's the same from the other side of the screen.
+    fn recognized(command: &Command, inputs: &[u8], buffer_frames: u32) -> bool {
+        let east = take(command, inputs, Direction::East, buffer_frames);
+        let mirrored: Vec<_> = inputs.iter().copied().map(mirror).collect();
+        let west = take(command, &mirrored, Direction::West, buffer_frames);
+        assert_eq!(east, west, "facing east and west disagree");
+        east
+    }
+
+    fn command(motion: Motion, button: Option<Button>, window: u32) -> Command {
+        Command {
+            motion: Some(motion),
+            button,
+            window,
+        }
+    }
+
+    #[test]
+    fn double_tap() {
+        let dash = command(Motion::DoubleTap(RelDir::Forward), None, 10);
+        assert!(recognized(&dash, &frames(&[(F, 2), (N, 3), (F, 1)]), 0));
+        // Held, rather than tapped twice.
+        assert!(!recognized(&dash, &frames(&[(F, 6)]), 0));
+        assert!(!recognized(&dash, &frames(&[(B, 2), (N, 3), (F, 1)]), 0));
+        // Only on the frame the second tap goes down.
+        assert!(!recognized(&dash, &frames(&[(F, 2), (N, 3), (F, 2)]), 0));
+    }
+
+    #[test]
+    fn double_tap_window() {
+        let dash = command(Motion::DoubleTap(RelDir::Forward), None, 10);
+        // The first tap 10 frames ago, then 11.
+        assert!(recognized(&dash, &frames(&[(F, 1), (N, 9), (F, 1)]), 0));
+        assert!(!recognized(&dash, &frames(&[(F, 1), (N, 10), (F, 1)]), 0));
+    }
+
+    #[test]
+    fn quarter_circle() {
+        let qcf = command(
+            Motion::QuarterCircle(RelDir::Forward),
+            Some(Button::Light),
+            10,
+        );
+        assert!(recognized(&qcf, &frames(&[(D, 2), (DF, 2), (F | L, 1)]), 0));
+        // Steps can be skipped over, but not left out.
+        assert!(recognized(
+            &qcf,
+            &frames(&[(D, 2), (N, 1), (DF, 1), (F | L, 1)]),
+            0
+        ));
+        assert!(!recognized(&qcf, &frames(&[(D, 2), (F | L, 1)]), 0));
+        assert!(!recognized(
+            &qcf,
+            &frames(&[(DF, 2), (D, 2), (F | L, 1)]),
+            0
+        ));
+        // Towards the back is a different motion.
+        let back = [(D, 2), (D | B, 2), (B | L, 1)];
+        assert!(!recognized(&qcf, &frames(&back), 0));
+        // Needs the button.
+        assert!(!recognized(&qcf, &frames(&[(D, 2), (DF, 2), (F, 1)]), 0));
+    }
+
+    #[test]
+    fn quarter_circle_from_the_wrong_side() {
+        let qcf = command(
+            Motion::QuarterCircle(RelDir::Forward),
+            Some(Button::Light),
+            10,
+        );
+        let inputs = frames(&[(D, 2), (DF, 2), (F | L, 1)]);
+        assert!(take(&qcf, &inputs, Direction::East, 0));
+        assert!(!take(&qcf, &inputs, Direction::West, 0));
+    }
+
+    #[test]
+    fn quarter_circle_window() {
+        let qcf = command(
+            Motion::QuarterCircle(RelDir::Forward),
+            Some(Button::Light),
+            10,
+        );
+        // Down 10 frames before the press, then 11.
+        let inside = frames(&[(D, 1), (DF, 1), (F, 8), (F | L, 1)]);
+        let outside = frames(&[(D, 1), (DF, 1), (F, 9), (F | L, 1)]);
+        assert!(recognized(&qcf, &inside, 0));
+        assert!(!recognized(&qcf, &outside, 0));
+    }
+
+    #[test]
+    fn quarter_circle_buffer() {
+        let qcf = command(
+            Motion::QuarterCircle(RelDir::Forward),
+            Some(Button::Light),
+            10,
+        );
+        // Pressed 2 frames ago.
+        let inputs = frames(&[(D, 1), (DF, 1), (F | L, 1), (N, 2)]);
+        assert!(recognized(&qcf, &inputs, 2));
+        assert!(!recognized(&qcf, &inputs, 1));
+    }
+
+    #[test]
+    fn quarter_circle_without_a_button_fires_once() {
+        let qcf = command(Motion::QuarterCircle(RelDir::Forward), None, 10);
+        assert!(recognized(&qcf, &frames(&[(D, 1), (DF, 1), (F, 1)]), 0));
+        assert!(!recognized(&qcf, &frames(&[(D, 1), (DF, 1), (F, 2)]), 0));
+    }
+
+    #[test]
+    fn press_is_only_used_once() {
+        let qcf = command(
+            Motion::QuarterCircle(RelDir::Forward),
+            Some(Button::Light),
+            10,
+        );
+        let mut history = InputHistory::default();
+        for input_bits in frames(&[(D, 1), (DF, 1), (F | L, 1)]) {
+            history.push(Input { input_bits });
+        }
+        assert!(qcf.take(&mut history, Direction::East, 0));
+        assert!(!qcf.take(&mut history, Direction::East, 0));
+    }
+
+    fn charge() -> Command {
+        let motion = Motion::Charge {
+            from: RelDir::Back,
+            frames: 30,
+            to: RelDir::Forward,
+        };
+        command(motion, Some(Button::Light), 10)
+    }
+
+    #[test]
+    fn charge_needs_the_whole_charge() {
+        assert!(recognized(&charge(), &frames(&[(B, 30), (F | L, 1)]), 0));
+        assert!(!recognized(&charge(), &frames(&[(B, 29), (F | L, 1)]), 0));
+        // Interrupted charges start over.
+        let interrupted = frames(&[(B, 20), (N, 1), (B, 20), (F | L, 1)]);
+        assert!(!recognized(&charge(), &interrupted, 0));
+        assert!(!recognized(&charge(), &frames(&[(B, 30), (B | L, 1)]), 0));
+    }
+
+    #[test]
+    fn charge_window() {
+        // Let go of the charge 10 frames before the press, then 11.
+        let inside = frames(&[(B, 30), (F, 9), (F | L, 1)]);
+        let outside = frames(&[(B, 30), (F, 10), (F | L, 1)]);
+        assert!(recognized(&charge(), &inside, 0));
+        assert!(!recognized(&charge(), &outside, 0));
+    }
+
+    #[test]
+    fn charge_buffer() {
+        let inputs = frames(&[(B, 30), (F | L, 1), (F, 3)]);
+        assert!(recognized(&charge(), &inputs, 3));
+        assert!(!recognized(&charge(), &inputs, 2));
+    }
+
+    #[test]
+    fn check_rejects_commands_too_long_for_the_history() {
+        let long = Command {
+            motion: Some(Motion::Charge {
+                from: RelDir::Back,
+                frames: INPUT_HISTORY_LEN as u32,
+                to: RelDir::Forward,
+            }),
+            button: None,
+            window: 0,
+        };
+        let result = std::panic::catch_unwind(|| check_command("long", &long));
+        assert!(result.is_err());
+        check_command("charge", &charge());
+    }
+}
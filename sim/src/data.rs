@@ -0,0 +1,135 @@
+use std::{ffi::OsStr, fs, path::Path};
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    animation::{animations_hash, load_animations, Animations, AttackData},
+    checksum::stable_hash,
+    moves::MoveTable,
+    player::CounterHit,
+};
+
+/// Name of the file in the assets that `Rules` are loaded from.
+pub const RULES_FILE: &str = "rules.ron";
+
+/// Name of the file in the assets that the `MoveTable` is loaded from.
+pub const MOVES_FILE: &str = "moves.ron";
+
+/// Health and stamina tuning shared by every character. Stamina is spent on
+/// moves (see each animation's `stamina_cost`) and on guarding, and once
+/// there isn't enough left to guard an attack, that attack breaks the guard.
+/// It also decays on its own, so that time spent in moves, stun and guarding
+/// wears it down, and only resting gains it back.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct Rules {
+    /// A player is knocked out once this much damage has been dealt to them.
+    pub max_health: u32,
+    pub max_stamina: u32,
+    /// Stamina lost every frame, whatever the player is doing.
+    pub stamina_decay: u32,
+    /// Stamina regained every frame while standing, walking forwards or
+    /// crouching, before `stamina_decay` is taken off.
+    pub stamina_regen: u32,
+    /// Stamina lost every frame while walking backwards, instead of regaining
+    /// it.
+    pub backwards_stamina_drain: u32,
+    /// Stamina lost on being hit, on top of the attack's `stamina_damage`.
+    pub hit_stamina_loss: u32,
+    /// Extra for hitting a player during the startup or active frames of
+    /// their move.
+    pub counter_hit: CounterBonus,
+    /// Extra for hitting a player while they recover from their move.
+    pub punish_counter: CounterBonus,
+    /// Damage taken off each hit in a combo, as a percentage, for every hit
+    /// before it.
+    pub combo_scaling: u32,
+    /// The least damage a hit in a combo deals, as a percentage of its own.
+    pub min_combo_scaling: u32,
+}
+
+/// What a counter-hit adds to the attack that lands it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct CounterBonus {
+    /// Extra damage, as a percentage of the attack's.
+    pub damage_percent: u32,
+    /// Extra frames of hitstun.
+    pub hitstun: u32,
+}
+
+impl Rules {
+    pub fn counter_bonus(&self, counter: CounterHit) -> &CounterBonus {
+        match counter {
+            CounterHit::Counter => &self.counter_hit,
+            CounterHit::Punish => &self.punish_counter,
+        }
+    }
+}
+
+impl CounterBonus {
+    pub fn apply(&self, attack: &AttackData) -> AttackData {
+        AttackData {
+            damage: attack.damage + attack.damage * self.damage_percent / 100,
+            hitstun: attack.hitstun + self.hitstun,
+            ..*attack
+        }
+    }
+}
+
+/// Everything the simulation needs besides inputs.
+pub struct GameData {
+    pub animations: Animations,
+    pub rules: Rules,
+    pub moves: MoveTable,
+}
+
+/// Loads the rules from `RULES_FILE`, the move table from `MOVES_FILE`, and
+/// everything else with `load_animations`.
+pub fn load_game_data<'a>(files: impl IntoIterator<Item = (&'a Path, &'a [u8])>) -> GameData {
+    let mut rules = None;
+    let mut moves = None;
+    let mut animation_files = Vec::new();
+    for (path, contents) in files {
+        let name = path.file_name();
+        if name == Some(OsStr::new(RULES_FILE)) {
+            rules = Some(parse(RULES_FILE, contents));
+        } else if name == Some(OsStr::new(MOVES_FILE)) {
+            moves = Some(parse::<MoveTable>(MOVES_FILE, contents));
+        } else {
+            animation_files.push((path, contents));
+        }
+    }
+
+    let animations = load_animations(animation_files);
+    let moves = moves.unwrap_or_else(|| panic!("missing {MOVES_FILE}"));
+    moves.check(&animations);
+    GameData {
+        animations,
+        rules: rules.unwrap_or_else(|| panic!("missing {RULES_FILE}")),
+        moves,
+    }
+}
+
+/// `load_game_data` on every file in the directory at `path`, for tools and
+/// tests that run next to the assets.
+pub fn load_game_data_from_dir(path: &Path) -> GameData {
+    let files: Vec<_> = fs::read_dir(path)
+        .unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let contents = fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+            (path, contents)
+        })
+        .collect();
+    load_game_data(files.iter().map(|(p, c)| (p.as_path(), c.as_slice())))
+}
+
+fn parse<T: DeserializeOwned>(name: &str, contents: &[u8]) -> T {
+    let contents = std::str::from_utf8(contents).unwrap();
+    ron::from_str(contents).unwrap_or_else(|e| panic!("{name}: {e}"))
+}
+
+/// Identifies a set of game data, so that replays can check they are being
+/// played back with the data they were recorded with.
+pub fn game_data_hash(data: &GameData) -> u64 {
+    stable_hash(&(animations_hash(&data.animations), &data.rules, &data.moves))
+}
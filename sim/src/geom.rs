@@ -0,0 +1,196 @@
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+};
+
+/// Fixed point number with 16 fractional bits.
+///
+/// All gameplay state uses this instead of floats, so that every peer
+/// (native or wasm) computes bit-identical results. Convert to `f32` only to
+/// render.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    const FRAC_BITS: u32 = 16;
+
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(1 << Self::FRAC_BITS);
+
+    pub const fn from_raw(raw: i32) -> Self {
+        Fixed(raw)
+    }
+
+    pub const fn raw(self) -> i32 {
+        self.0
+    }
+
+    pub const fn from_int(n: i32) -> Self {
+        Fixed(n << Self::FRAC_BITS)
+    }
+
+    /// `num / den`, rounded towards zero.
+    pub const fn from_ratio(num: i32, den: i32) -> Self {
+        Fixed((((num as i64) << Self::FRAC_BITS) / den as i64) as i32)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / Self::ONE.0 as f32
+    }
+
+    pub fn clamp(self, min: Fixed, max: Fixed) -> Self {
+        Fixed(self.0.clamp(min.0, max.0))
+    }
+}
+
+/// Shows the approximate value, followed by the exact raw value.
+impl fmt::Debug for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.to_f32(), self.0)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Fixed) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Fixed) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(-self.0)
+    }
+}
+
+impl Mul<i32> for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, rhs: i32) -> Fixed {
+        Fixed(self.0 * rhs)
+    }
+}
+
+impl Div<i32> for Fixed {
+    type Output = Fixed;
+
+    fn div(self, rhs: i32) -> Fixed {
+        Fixed(self.0 / rhs)
+    }
+}
+
+/// World space. The stage is [-1, 1] horizontally, y is up, and 0 is the floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Vec2 {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2 {
+        x: Fixed::ZERO,
+        y: Fixed::ZERO,
+    };
+
+    pub const fn new(x: Fixed, y: Fixed) -> Self {
+        Vec2 { x, y }
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, rhs: Vec2) -> Vec2 {
+        Vec2 {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, rhs: Vec2) {
+        *self = *self + rhs;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+
+    /// How far the two overlap horizontally, if they overlap at all (not
+    /// just touch).
+    pub fn x_overlap(&self, other: &Aabb) -> Option<Fixed> {
+        let overlaps_y = self.min.y < other.max.y && other.min.y < self.max.y;
+        let overlap = self.max.x.min(other.max.x) - self.min.x.max(other.min.x);
+        (overlaps_y && overlap > Fixed::ZERO).then_some(overlap)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2 {
+            x: (self.min.x + self.max.x) / 2,
+            y: (self.min.y + self.max.y) / 2,
+        }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2 {
+            x: self.max.x - self.min.x,
+            y: self.max.y - self.min.y,
+        }
+    }
+
+    /// Mirror around x = 0, for players facing west.
+    pub fn reflect_x(self) -> Self {
+        Aabb {
+            min: Vec2 {
+                x: -self.max.x,
+                y: self.min.y,
+            },
+            max: Vec2 {
+                x: -self.min.x,
+                y: self.max.y,
+            },
+        }
+    }
+
+    pub fn translate(self, by: Vec2) -> Self {
+        Aabb {
+            min: self.min + by,
+            max: self.max + by,
+        }
+    }
+}
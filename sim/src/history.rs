@@ -0,0 +1,132 @@
+use crate::Input;
+
+/// Frames of input kept per player. Enough for the buffer window and for
+/// reading motions, including charges, out of.
+pub const INPUT_HISTORY_LEN: usize = 64;
+
+/// A player's most recent inputs, newest first. Part of the rollback state,
+/// so that buffered presses are replayed identically.
+#[derive(Debug, Clone, Hash)]
+pub struct InputHistory {
+    inputs: [Input; INPUT_HISTORY_LEN],
+    /// Per frame, the buttons whose press on that frame has already been
+    /// acted on, so one press can't be buffered into two actions.
+    consumed: [u8; INPUT_HISTORY_LEN],
+}
+
+impl Default for InputHistory {
+    fn default() -> Self {
+        InputHistory {
+            inputs: [Input::default(); INPUT_HISTORY_LEN],
+            consumed: [0; INPUT_HISTORY_LEN],
+        }
+    }
+}
+
+impl InputHistory {
+    pub(crate) fn push(&mut self, input: Input) {
+        self.inputs.rotate_right(1);
+        self.consumed.rotate_right(1);
+        self.inputs[0] = input;
+        self.consumed[0] = 0;
+    }
+
+    /// The input `frames_ago` frames before the current one. Anything older
+    /// than the history is neutral.
+    pub fn get(&self, frames_ago: usize) -> Input {
+        self.inputs.get(frames_ago).copied().unwrap_or_default()
+    }
+
+    /// Whether any of `bits` went down `frames_ago` frames before the
+    /// current one.
+    pub fn pressed(&self, bits: u8, frames_ago: usize) -> bool {
+        self.get(frames_ago).input_bits & !self.get(frames_ago + 1).input_bits & bits != 0
+    }
+
+    /// Whether `button` went down `frames_ago` frames before the current one,
+    /// and that press hasn't been acted on yet.
+    pub(crate) fn unused_press(&self, button: u8, frames_ago: usize) -> bool {
+        frames_ago < INPUT_HISTORY_LEN
+            && self.pressed(button, frames_ago)
+            && self.consumed[frames_ago] & button == 0
+    }
+
+    pub(crate) fn consume(&mut self, button: u8, frames_ago: usize) {
+        self.consumed[frames_ago] |= button;
+    }
+
+    /// Finds the most recent press of `button` within the last `window`
+    /// frames (0 meaning this frame only) that hasn't been acted on yet, and
+    /// marks it as acted on.
+    pub(crate) fn take_press(&mut self, button: u8, window: u32) -> bool {
+        let found = (0..=window as usize).find(|&frames_ago| self.unused_press(button, frames_ago));
+        if let Some(frames_ago) = found {
+            self.consume(button, frames_ago);
+        }
+        found.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A history of `bits` pressed on one frame, `frames_ago` frames before
+    /// the current one.
+    fn pressed_ago(bits: u8, frames_ago: usize) -> InputHistory {
+        let mut history = InputHistory::default();
+        history.push(Input { input_bits: bits });
+        for _ in 0..frames_ago {
+            history.push(Input::default());
+        }
+        history
+    }
+
+    #[test]
+    fn a_press_in_the_window_fires_once() {
+        for frames_ago in 0..=4 {
+            let mut history = pressed_ago(Input::LIGHT, frames_ago);
+            assert!(history.take_press(Input::LIGHT, 4), "{frames_ago} ago");
+            assert!(!history.take_press(Input::LIGHT, 4), "{frames_ago} ago");
+        }
+    }
+
+    #[test]
+    fn a_press_outside_the_window_does_not_fire() {
+        let mut history = pressed_ago(Input::LIGHT, 5);
+        assert!(!history.take_press(Input::LIGHT, 4));
+        // Still there for a longer window.
+        assert!(history.take_press(Input::LIGHT, 5));
+    }
+
+    #[test]
+    fn holding_a_button_is_one_press() {
+        let mut history = InputHistory::default();
+        for _ in 0..3 {
+            history.push(Input {
+                input_bits: Input::LIGHT,
+            });
+        }
+        assert!(history.take_press(Input::LIGHT, 4));
+        assert!(!history.take_press(Input::LIGHT, 4));
+    }
+
+    #[test]
+    fn buttons_are_consumed_separately() {
+        let mut history = pressed_ago(Input::LIGHT | Input::MEDIUM, 1);
+        assert!(history.take_press(Input::LIGHT, 4));
+        assert!(history.take_press(Input::MEDIUM, 4));
+        assert!(!history.take_press(Input::LIGHT | Input::MEDIUM, 4));
+    }
+
+    #[test]
+    fn the_newest_press_is_taken_first() {
+        let mut history = pressed_ago(Input::LIGHT, 3);
+        history.push(Input {
+            input_bits: Input::LIGHT,
+        });
+        assert!(history.take_press(Input::LIGHT, 0));
+        // The older press is still there to buffer.
+        assert!(history.take_press(Input::LIGHT, 4));
+    }
+}
@@ -0,0 +1,1233 @@
+//! The fight simulation, with no dependency on a window, GPU or network.
+//!
+//! Everything here is advanced one frame at a time from the inputs of both
+//! players, so it can be driven by GGRS, tests, bots or a server alike.
+//! Rendering reads the simulated state but never feeds back into it.
+
+mod animation;
+mod checksum;
+mod combo;
+mod command;
+mod data;
+mod geom;
+mod history;
+mod moves;
+mod player;
+mod projectile;
+mod replay;
+#[cfg(test)]
+mod testing;
+
+use std::{mem, ops::ControlFlow};
+
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+
+pub use animation::{
+    animations_hash, load_animations, Animation, AnimationData, Animations, AnnotatedSprite,
+    AttackData, AttackHeight, Cancel, CancelOn, Contact, MovePhase, PixelRect, Spawn, Stance,
+    ThrowData,
+};
+pub use checksum::stable_hash;
+pub use combo::Combo;
+pub use command::{Button, Command, Motion, RelDir};
+pub use data::{
+    game_data_hash, load_game_data, load_game_data_from_dir, CounterBonus, GameData, Rules,
+    MOVES_FILE, RULES_FILE,
+};
+pub use geom::{Aabb, Fixed, Vec2};
+pub use history::{InputHistory, INPUT_HISTORY_LEN};
+pub use moves::{MoveState, MoveTable, Normal};
+pub use player::{
+    CounterHit, Direction, JumpArc, Player, PlayerState, GRAVITY, JUMP_SPEED, JUMP_X_SPEED,
+    KNOCKBACK_FRICTION, PLAYER_SPEED, THROW_ESCAPE_PUSHBACK,
+};
+pub use projectile::Projectile;
+pub use replay::{Replay, REPLAY_VERSION};
+
+pub const START_LOCATIONS: [Vec2; 2] = [
+    Vec2::new(Fixed::from_ratio(-1, 2), Fixed::ZERO),
+    Vec2::new(Fixed::from_ratio(1, 2), Fixed::ZERO),
+];
+
+/// Frames of countdown before each round, during which nobody can act.
+pub const COUNTDOWN_FRAMES: u32 = 3 * 60;
+
+/// Frames the result of a round is shown before the next one starts.
+pub const ROUND_OVER_FRAMES: u32 = 2 * 60;
+
+/// Frames after the match ends before rematch votes count, so that players
+/// still mashing attack don't vote by accident.
+pub const REMATCH_LOCKOUT_FRAMES: u32 = 60;
+
+/// Frames a player can't be hit for after losing a stock and respawning.
+pub const RESPAWN_INVULNERABLE_FRAMES: u32 = 90;
+
+/// Agreed on by both peers before the match starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MatchSettings {
+    /// Rounds needed to win the match. Only used with `StockMode::PerRound`.
+    pub first_to: u32,
+    /// Lives each player has, per round or for the whole match depending on
+    /// `stock_mode`. Losing one respawns the player.
+    pub stocks: u32,
+    pub stock_mode: StockMode,
+    /// How many frames early an attack can be pressed and still come out
+    /// once the player can act.
+    pub buffer_frames: u32,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            first_to: 2,
+            stocks: 1,
+            stock_mode: StockMode::PerRound,
+            buffer_frames: 4,
+        }
+    }
+}
+
+impl MatchSettings {
+    /// Settings can come from outside the game, such as a replay file, so
+    /// they're checked before a match is started with them.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.first_to == 0 {
+            return Err("matches have to be first to at least 1 round".into());
+        }
+        if self.stocks == 0 {
+            return Err("players have to start with at least 1 stock".into());
+        }
+        Ok(())
+    }
+}
+
+/// What running out of stocks costs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StockMode {
+    /// Stocks are refilled every round, and losing the last one loses the
+    /// round. The match goes on until someone has won `first_to` rounds.
+    PerRound,
+    /// Stocks last the whole match, which is a single round, and losing the
+    /// last one loses the match.
+    PerMatch,
+}
+
+#[derive(Debug, Clone, Hash)]
+pub struct GameState {
+    pub settings: MatchSettings,
+    /// Starts at 1.
+    pub round: u32,
+    pub wins: [u32; 2],
+    /// Lives left, this round or this match depending on the stock mode.
+    pub stocks: [u32; 2],
+    pub phase: Phase,
+}
+
+#[derive(Debug, Clone, Hash)]
+pub enum Phase {
+    /// Players are back in their start positions, but can't act yet.
+    Countdown {
+        frames_left: u32,
+        playing: PlayingState,
+    },
+    Playing(PlayingState),
+    /// Between rounds.
+    RoundOver {
+        /// `None` for a draw, when both players lost their last stock at
+        /// once. A drawn round doesn't count for either of them.
+        winner: Option<usize>,
+        frames_left: u32,
+    },
+    /// A new match starts once both players vote for a rematch, by pressing
+    /// attack.
+    MatchOver {
+        /// `None` for a draw, when both players lost their last stock at
+        /// once.
+        winner: Option<usize>,
+        frames: u32,
+        rematch_votes: [bool; 2],
+    },
+}
+
+#[derive(Debug, Clone, Hash)]
+pub struct PlayingState {
+    pub players: [Player; 2],
+    /// In the order they were fired.
+    pub projectiles: Vec<Projectile>,
+}
+
+#[repr(C)]
+#[derive(
+    Debug, Default, PartialEq, Eq, Hash, Copy, Clone, Pod, Zeroable, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Input {
+    /// Bit 0: left
+    ///
+    /// Bit 1: right
+    ///
+    /// Bit 2: light attack
+    ///
+    /// Bit 3: jump
+    ///
+    /// Bit 4: down
+    ///
+    /// Bit 5: throw
+    ///
+    /// Bit 6: medium attack
+    ///
+    /// Bit 7: heavy attack
+    // TODO: Bitfield crate? Needs to be ": Pod"
+    pub input_bits: u8,
+}
+
+impl Input {
+    pub const LEFT: u8 = 0b001;
+    pub const RIGHT: u8 = 0b010;
+    pub const LIGHT: u8 = 0b100;
+    pub const JUMP: u8 = 0b1000;
+    pub const DOWN: u8 = 0b1_0000;
+    pub const THROW: u8 = 0b10_0000;
+    pub const MEDIUM: u8 = 0b100_0000;
+    pub const HEAVY: u8 = 0b1000_0000;
+    pub const ATTACKS: u8 = Self::LIGHT | Self::MEDIUM | Self::HEAVY;
+
+    fn is_attack_pressed(self) -> bool {
+        self.input_bits & Self::ATTACKS != 0
+    }
+
+    fn is_left_pressed(self) -> bool {
+        self.input_bits & Self::LEFT != 0
+    }
+
+    fn is_right_pressed(self) -> bool {
+        self.input_bits & Self::RIGHT != 0
+    }
+
+    fn is_jump_pressed(self) -> bool {
+        self.input_bits & Self::JUMP != 0
+    }
+
+    fn is_down_pressed(self) -> bool {
+        self.input_bits & Self::DOWN != 0
+    }
+}
+
+impl GameState {
+    /// Fails if the settings aren't valid.
+    pub fn new(settings: MatchSettings, data: &GameData) -> Result<Self, String> {
+        settings.validate()?;
+        Ok(GameState::start(settings, data))
+    }
+
+    /// `new`, for settings that have already been validated.
+    fn start(settings: MatchSettings, data: &GameData) -> Self {
+        GameState {
+            settings,
+            round: 1,
+            wins: [0, 0],
+            stocks: [settings.stocks; 2],
+            phase: Phase::countdown(data),
+        }
+    }
+
+    /// Identical on every peer that has simulated the same inputs, for desync
+    /// detection.
+    pub fn checksum(&self) -> u128 {
+        stable_hash(self) as u128
+    }
+
+    pub fn advance_frame(&mut self, inputs: [Input; 2], data: &GameData) {
+        match &mut self.phase {
+            Phase::Countdown {
+                frames_left,
+                playing,
+            } => {
+                // Keep animating, but ignore what the players are pressing.
+                playing.update([Input::default(); 2], &self.settings, data);
+                *frames_left -= 1;
+                if *frames_left == 0 {
+                    self.phase = Phase::Playing(mem::replace(playing, PlayingState::new(data)));
+                }
+            }
+            Phase::Playing(playing_state) => {
+                let knocked_out = playing_state.update(inputs, &self.settings, data);
+                if knocked_out == [false, false] {
+                    return;
+                }
+                for (stocks, knocked_out) in self.stocks.iter_mut().zip(knocked_out) {
+                    if knocked_out {
+                        *stocks -= 1;
+                    }
+                }
+                let winner = match self.stocks {
+                    [0, 0] => None,
+                    [0, _] => Some(1),
+                    [_, 0] => Some(0),
+                    _ => {
+                        for i in (0..2).filter(|&i| knocked_out[i]) {
+                            playing_state.respawn(i, data);
+                        }
+                        return;
+                    }
+                };
+                if let Some(winner) = winner {
+                    self.wins[winner] += 1;
+                }
+                let match_over = match self.settings.stock_mode {
+                    StockMode::PerRound => {
+                        winner.is_some_and(|winner| self.wins[winner] >= self.settings.first_to)
+                    }
+                    StockMode::PerMatch => true,
+                };
+                self.phase = if match_over {
+                    Phase::MatchOver {
+                        winner,
+                        frames: 0,
+                        rematch_votes: [false, false],
+                    }
+                } else {
+                    Phase::RoundOver {
+                        winner,
+                        frames_left: ROUND_OVER_FRAMES,
+                    }
+                };
+            }
+            Phase::RoundOver { frames_left, .. } => {
+                *frames_left -= 1;
+                if *frames_left == 0 {
+                    self.round += 1;
+                    self.stocks = [self.settings.stocks; 2];
+                    self.phase = Phase::countdown(data);
+                }
+            }
+            Phase::MatchOver {
+                frames,
+                rematch_votes,
+                ..
+            } => {
+                *frames = frames.saturating_add(1);
+                if *frames >= REMATCH_LOCKOUT_FRAMES {
+                    for (vote, input) in rematch_votes.iter_mut().zip(inputs) {
+                        *vote |= input.is_attack_pressed();
+                    }
+                }
+                if *rematch_votes == [true, true] {
+                    *self = GameState::start(self.settings, data);
+                }
+            }
+        }
+    }
+}
+
+impl Phase {
+    fn countdown(data: &GameData) -> Self {
+        Phase::Countdown {
+            frames_left: COUNTDOWN_FRAMES,
+            playing: PlayingState::new(data),
+        }
+    }
+
+    /// The fight, if one is on screen.
+    pub fn playing_state(&self) -> Option<&PlayingState> {
+        match self {
+            Phase::Countdown { playing, .. } | Phase::Playing(playing) => Some(playing),
+            Phase::RoundOver { .. } | Phase::MatchOver { .. } => None,
+        }
+    }
+}
+
+impl PlayingState {
+    pub fn new(data: &GameData) -> Self {
+        let (rules, anims) = (&data.rules, &data.animations);
+        PlayingState {
+            players: [
+                Player::new(START_LOCATIONS[0], Direction::East, rules, anims),
+                Player::new(START_LOCATIONS[1], Direction::West, rules, anims),
+            ],
+            projectiles: Vec::new(),
+        }
+    }
+
+    /// Returns which players were knocked out, once their death animations
+    /// are over. After a trade, that can be both of them.
+    pub fn update(
+        &mut self,
+        inputs: [Input; 2],
+        settings: &MatchSettings,
+        data: &GameData,
+    ) -> [bool; 2] {
+        let (rules, moves, anims) = (&data.rules, &data.moves, &data.animations);
+        for (p, input) in self.players.iter_mut().zip(inputs) {
+            p.inputs.push(input);
+        }
+
+        // Transition states
+
+        let mut knocked_out = [false, false];
+        for (i, p) in self.players.iter_mut().enumerate() {
+            p.invulnerable = p.invulnerable.saturating_sub(1);
+            let animation_done = matches!(p.animation.next_frame(), ControlFlow::Break(()));
+            // Stun lasts as long as the attack says, however long the
+            // animation is.
+            let done = if p.stun > 0 {
+                p.stun -= 1;
+                p.stun == 0
+            } else {
+                animation_done
+            };
+            if done {
+                p.counter_hit = None;
+                match p.state {
+                    PlayerState::Death => knocked_out[i] = true,
+                    // Nobody escaped in time.
+                    PlayerState::Thrown(attack) => p.take_hit(&attack, rules, anims),
+                    PlayerState::JumpStartup(arc) => p.take_off(arc, anims),
+                    _ if p.is_airborne() => p.start_falling(anims),
+                    _ if p.animation.throw().is_some() => p.start_throw_whiff(anims),
+                    _ => p.start_idle(),
+                }
+            }
+        }
+        if knocked_out != [false, false] {
+            return knocked_out;
+        }
+
+        if self.players[0].loc.x < self.players[1].loc.x {
+            self.players[0].facing = Direction::East;
+            self.players[1].facing = Direction::West;
+        } else if self.players[0].loc.x > self.players[1].loc.x {
+            self.players[0].facing = Direction::West;
+            self.players[1].facing = Direction::East;
+        }
+        // Else, equal, change nothing.
+
+        // HANDLE INPUT
+
+        // NOTE: start_idle may not be called after this point.
+        // we rely on input handling to put us in the right walking animation.
+
+        for (i, input) in inputs.into_iter().enumerate() {
+            let mut x_accel = self.players[i].animation.speed().unwrap_or(Fixed::ZERO);
+            if matches!(
+                self.players[i].state,
+                PlayerState::Idle | PlayerState::Crouching
+            ) {
+                let crouch = input.is_down_pressed()
+                    && !input.is_jump_pressed()
+                    && self.players[i].loc.y == Fixed::ZERO;
+
+                if self.players[i].try_command(Stance::Ground, settings.buffer_frames, anims)
+                    || self.players[i].try_normal(moves, settings.buffer_frames, anims)
+                {
+                    x_accel = self.players[i].animation.speed().unwrap_or(Fixed::ZERO);
+                } else if crouch {
+                    self.players[i].ensure_crouching(anims);
+                } else {
+                    let left = input.is_left_pressed();
+                    let right = input.is_right_pressed();
+                    let jump = input.is_jump_pressed();
+
+                    let forwards = (left && (self.players[i].facing == Direction::West))
+                        || (right && (self.players[i].facing == Direction::East));
+                    let backwards = (left && (self.players[i].facing == Direction::East))
+                        || (right && (self.players[i].facing == Direction::West));
+
+                    match (forwards, backwards, jump) {
+                        (_, _, true) => {
+                            let arc = match (forwards, backwards) {
+                                (true, false) => JumpArc::Forward,
+                                (false, true) => JumpArc::Back,
+                                _ => JumpArc::Neutral,
+                            };
+                            self.players[i].start_jump(arc, anims);
+                        }
+                        (true, false, _) => {
+                            x_accel = PLAYER_SPEED;
+                            self.players[i].ensure_walking_forwards(anims);
+                        }
+                        (false, true, _) => {
+                            x_accel = -PLAYER_SPEED;
+                            self.players[i].ensure_walking_backwards(anims);
+                        }
+                        // Our clients resolve left + right before sending
+                        // inputs, but standing still is the safe fallback.
+                        (true, true, _) | (false, false, _) => {
+                            self.players[i].ensure_standing(anims);
+                        }
+                    }
+                }
+            } else if matches!(
+                self.players[i].state,
+                PlayerState::Attacking | PlayerState::Command
+            ) {
+                if self.players[i].try_cancel(moves, settings.buffer_frames, anims) {
+                    x_accel = self.players[i].animation.speed().unwrap_or(Fixed::ZERO);
+                }
+            } else if self.players[i].state == PlayerState::Airborne {
+                // What can be done in the air is entirely up to the data.
+                if !self.players[i].try_command(Stance::Air, settings.buffer_frames, anims) {
+                    self.players[i].try_normal(moves, settings.buffer_frames, anims);
+                }
+            } else if matches!(self.players[i].state, PlayerState::Thrown(_))
+                && self.players[i]
+                    .inputs
+                    .take_press(Input::THROW, settings.buffer_frames)
+            {
+                for p in &mut self.players {
+                    p.start_throw_escape(anims);
+                }
+            }
+            self.players[i].accelerate(x_accel);
+        }
+
+        for p in &mut self.players {
+            p.update_stamina(rules, anims);
+            p.update_loc(anims);
+        }
+        self.push_apart();
+
+        self.projectiles
+            .retain_mut(|projectile| projectile.update());
+        for (i, p) in self.players.iter().enumerate() {
+            if let Some(spawn) = p.animation.spawn() {
+                self.projectiles.push(Projectile::new(i, p, spawn, anims));
+            }
+        }
+
+        // Handle throws, before attacks so that a grabbed player's attack is
+        // cut short.
+        let hurtboxes = self.hurtboxes();
+        let grabboxes = self.grabboxes();
+        let grabs: [Option<ThrowData>; 2] = [0, 1].map(|i| {
+            let connects = grabboxes[i]
+                .zip(hurtboxes[1 - i])
+                .is_some_and(|(grab, hurt)| grab.intersects(&hurt));
+            let throw = self.players[i].animation.throw();
+            throw
+                .filter(|_| connects && self.players[1 - i].is_throwable())
+                .cloned()
+        });
+        match grabs {
+            [Some(_), Some(_)] => {
+                // Grabbing each other cancels out, like an escape.
+                for p in &mut self.players {
+                    p.start_throw_escape(anims);
+                }
+            }
+            [Some(throw), None] => {
+                self.players[0].start_throw_success(&throw, anims);
+                self.players[1].start_thrown(&throw, anims);
+            }
+            [None, Some(throw)] => {
+                self.players[1].start_throw_success(&throw, anims);
+                self.players[0].start_thrown(&throw, anims);
+            }
+            [None, None] => (),
+        }
+
+        // Handle attacks
+        let hurtboxes = self.hurtboxes();
+        let hitboxes = self.hitboxes();
+
+        // What each player's attack does to the other, if it connects.
+        let hits: [Option<AttackData>; 2] = [0, 1].map(|i| {
+            let connects = hitboxes[i]
+                .zip(hurtboxes[1 - i])
+                .is_some_and(|(hit, hurt)| hit.intersects(&hurt));
+            connects.then(|| self.players[i].attack()).flatten()
+        });
+        match hits {
+            [Some(hit_1), Some(hit_0)] => {
+                // A trade, neither can guard.
+                self.players[0].animation.land(&hit_1, Contact::Hit);
+                self.players[1].animation.land(&hit_0, Contact::Hit);
+                self.players[0].take_hit(&hit_0, rules, anims);
+                self.players[1].take_hit(&hit_1, rules, anims);
+            }
+            [Some(attack), None] => {
+                let height = self.players[0].animation.height();
+                let contact = self.players[1].handle_hit(height, &attack, rules, anims);
+                self.players[0].animation.land(&attack, contact);
+            }
+            [None, Some(attack)] => {
+                let height = self.players[1].animation.height();
+                let contact = self.players[0].handle_hit(height, &attack, rules, anims);
+                self.players[1].animation.land(&attack, contact);
+            }
+            [None, None] => (),
+        }
+
+        // At most one hit a frame: whoever was just hit (or made to guard)
+        // in melee is left alone by projectiles until the next.
+        let struck = [hits[1].is_some(), hits[0].is_some()];
+        self.handle_projectiles(struck, rules, anims);
+
+        [false, false]
+    }
+
+    /// Projectiles of different players that meet cancel each other out, one
+    /// for one. The rest hit whoever they reach, like any other attack, and
+    /// are spent doing so, unless that player is one of those already
+    /// `struck` this frame.
+    fn handle_projectiles(&mut self, mut struck: [bool; 2], rules: &Rules, anims: &Animations) {
+        let hitboxes: Vec<_> = self.projectiles.iter().map(|p| p.hitbox()).collect();
+        let mut spent = vec![false; self.projectiles.len()];
+        for a in 0..self.projectiles.len() {
+            for b in a + 1..self.projectiles.len() {
+                let clash = self.projectiles[a].owner != self.projectiles[b].owner
+                    && hitboxes[a]
+                        .zip(hitboxes[b])
+                        .is_some_and(|(a, b)| a.intersects(&b));
+                if clash && !spent[a] && !spent[b] {
+                    spent[a] = true;
+                    spent[b] = true;
+                }
+            }
+        }
+
+        let hurtboxes = self.hurtboxes();
+        for (j, projectile) in self.projectiles.iter().enumerate() {
+            let target = 1 - projectile.owner;
+            let connects = hitboxes[j]
+                .zip(hurtboxes[target])
+                .is_some_and(|(hit, hurt)| hit.intersects(&hurt));
+            if spent[j] || struck[target] || !connects {
+                continue;
+            }
+            if let Some(attack) = projectile.attack() {
+                self.players[target].handle_hit(projectile.height(), &attack, rules, anims);
+                spent[j] = true;
+                struck[target] = true;
+            }
+        }
+
+        let mut spent = spent.into_iter();
+        self.projectiles.retain(|_| !spent.next().unwrap());
+    }
+
+    /// Puts a knocked out player back at their start location, fresh, and
+    /// briefly invulnerable.
+    fn respawn(&mut self, i: usize, data: &GameData) {
+        let facing = self.players[i].facing;
+        self.players[i] = Player::new(START_LOCATIONS[i], facing, &data.rules, &data.animations);
+        self.players[i].invulnerable = RESPAWN_INVULNERABLE_FRAMES;
+    }
+
+    /// Moves the players out of each other, half each, unless one is in the
+    /// corner, in which case the other one is moved the whole way.
+    fn push_apart(&mut self) {
+        let [a, b] = self.pushboxes();
+        let Some(overlap) = a.x_overlap(&b) else {
+            return;
+        };
+
+        // Players on the same spot are separated by who was facing which way,
+        // which is still from the last frame they weren't.
+        let (p0, p1) = (&self.players[0], &self.players[1]);
+        let left = if p0.loc.x != p1.loc.x {
+            usize::from(p0.loc.x > p1.loc.x)
+        } else {
+            usize::from(p0.facing == Direction::West)
+        };
+        let right = 1 - left;
+
+        let half = overlap / 2;
+        self.players[left].loc.x -= half;
+        self.players[right].loc.x += overlap - half;
+
+        if self.players[left].loc.x < -Fixed::ONE {
+            let excess = -Fixed::ONE - self.players[left].loc.x;
+            self.players[left].loc.x = -Fixed::ONE;
+            self.players[right].loc.x += excess;
+        }
+        if self.players[right].loc.x > Fixed::ONE {
+            let excess = self.players[right].loc.x - Fixed::ONE;
+            self.players[right].loc.x = Fixed::ONE;
+            self.players[left].loc.x -= excess;
+        }
+    }
+
+    pub fn pushboxes(&self) -> [Aabb; 2] {
+        self.players.each_ref().map(|p| p.pushbox())
+    }
+
+    pub fn grabboxes(&self) -> [Option<Aabb>; 2] {
+        self.players.each_ref().map(|p| p.grabbox())
+    }
+
+    pub fn hitboxes(&self) -> [Option<Aabb>; 2] {
+        self.players.each_ref().map(|p| p.hitbox())
+    }
+
+    pub fn hurtboxes(&self) -> [Option<Aabb>; 2] {
+        self.players.each_ref().map(|p| p.hurtbox())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fight;
+
+    #[test]
+    fn rejects_settings_that_would_end_before_starting() {
+        let data = crate::testing::game_data();
+        for settings in [
+            MatchSettings {
+                first_to: 0,
+                ..MatchSettings::default()
+            },
+            MatchSettings {
+                stocks: 0,
+                ..MatchSettings::default()
+            },
+        ] {
+            assert!(GameState::new(settings, &data).is_err());
+        }
+    }
+
+    #[test]
+    fn countdown_ends_in_play() {
+        let fight = Fight::new(MatchSettings::default());
+        assert_eq!(fight.state.round, 1);
+        for (i, start) in START_LOCATIONS.into_iter().enumerate() {
+            assert_eq!(fight.player(i).state, PlayerState::Idle);
+            assert_eq!(fight.player(i).loc, start);
+        }
+    }
+
+    /// Knocks player `i` out with a hit, and waits for their death animation
+    /// to finish.
+    fn knock_out(fight: &mut Fight, i: usize) {
+        let attack = AttackData {
+            damage: 1000,
+            hitstun: 30,
+            blockstun: 20,
+            guard_break_stun: 120,
+            pushback: 0,
+            stamina_damage: 0,
+            hit: 0,
+        };
+        let stocks = fight.state.stocks[i];
+        fight.hit(i, AttackHeight::Mid, &attack);
+        assert_eq!(fight.player(i).state, PlayerState::Death);
+        while fight.state.stocks[i] == stocks {
+            fight.step([0, 0]);
+        }
+    }
+
+    #[test]
+    fn per_round_stocks_lose_the_round_and_refill() {
+        let mut fight = Fight::new(MatchSettings {
+            stocks: 2,
+            stock_mode: StockMode::PerRound,
+            ..MatchSettings::default()
+        });
+        knock_out(&mut fight, 1);
+        assert_eq!(fight.state.stocks, [2, 1]);
+        assert!(fight.player(1).invulnerable > 0);
+
+        fight.run([0, 0], RESPAWN_INVULNERABLE_FRAMES);
+        knock_out(&mut fight, 1);
+        assert!(matches!(
+            fight.state.phase,
+            Phase::RoundOver {
+                winner: Some(0),
+                ..
+            }
+        ));
+        fight.run([0, 0], ROUND_OVER_FRAMES + COUNTDOWN_FRAMES);
+        assert_eq!(fight.state.round, 2);
+        assert_eq!(fight.state.stocks, [2, 2]);
+    }
+
+    #[test]
+    fn per_match_stocks_end_the_match() {
+        let mut fight = Fight::new(MatchSettings {
+            first_to: 3,
+            stocks: 2,
+            stock_mode: StockMode::PerMatch,
+            ..MatchSettings::default()
+        });
+        knock_out(&mut fight, 1);
+        assert!(matches!(fight.state.phase, Phase::Playing(_)));
+        fight.run([0, 0], RESPAWN_INVULNERABLE_FRAMES);
+        knock_out(&mut fight, 1);
+        assert!(matches!(
+            fight.state.phase,
+            Phase::MatchOver {
+                winner: Some(0),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn a_hitbox_out_for_several_frames_hits_once() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.player_mut(0).loc.x = Fixed::from_ratio(-1, 10);
+        fight.player_mut(1).loc.x = Fixed::from_ratio(1, 10);
+        fight.step([Input::LIGHT, 0]);
+        let mut frames_touching = 0;
+        while fight.player(0).state == PlayerState::Attacking {
+            let [hitbox, _] = fight.playing().hitboxes();
+            let [_, hurtbox] = fight.playing().hurtboxes();
+            if hitbox
+                .zip(hurtbox)
+                .is_some_and(|(hit, hurt)| hit.intersects(&hurt))
+            {
+                frames_touching += 1;
+            }
+            fight.step([0, 0]);
+        }
+        assert!(frames_touching > 1);
+        assert_eq!(fight.player(1).combo.hits, 1);
+    }
+
+    /// Both players on 1 health trade light attacks, and their death
+    /// animations play out.
+    fn double_ko(fight: &mut Fight) {
+        fight.player_mut(0).loc.x = Fixed::from_ratio(-1, 10);
+        fight.player_mut(1).loc.x = Fixed::from_ratio(1, 10);
+        for i in 0..2 {
+            fight.player_mut(i).health = 1;
+        }
+        let stocks = fight.state.stocks;
+        fight.step([Input::LIGHT; 2]);
+        while fight.state.stocks == stocks {
+            fight.step([0, 0]);
+        }
+    }
+
+    #[test]
+    fn a_double_ko_on_the_last_stocks_draws_the_round() {
+        let mut fight = Fight::new(MatchSettings::default());
+        double_ko(&mut fight);
+        assert!(matches!(
+            fight.state.phase,
+            Phase::RoundOver { winner: None, .. }
+        ));
+        assert_eq!(fight.state.wins, [0, 0]);
+        fight.run([0, 0], ROUND_OVER_FRAMES + COUNTDOWN_FRAMES);
+        assert_eq!(fight.state.round, 2);
+    }
+
+    #[test]
+    fn a_double_ko_takes_a_stock_from_both_players() {
+        let mut fight = Fight::new(MatchSettings {
+            stocks: 2,
+            stock_mode: StockMode::PerMatch,
+            ..MatchSettings::default()
+        });
+        double_ko(&mut fight);
+        assert_eq!(fight.state.stocks, [1, 1]);
+        assert!(matches!(fight.state.phase, Phase::Playing(_)));
+
+        fight.run([0, 0], RESPAWN_INVULNERABLE_FRAMES);
+        double_ko(&mut fight);
+        assert!(matches!(
+            fight.state.phase,
+            Phase::MatchOver { winner: None, .. }
+        ));
+    }
+
+    /// Player 1 throws player 2 from up close, holding `p2` after the grab.
+    fn throw(p2: u8) -> Fight {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.player_mut(0).loc.x = Fixed::from_ratio(-1, 10);
+        fight.player_mut(1).loc.x = Fixed::from_ratio(1, 10);
+        fight.step([Input::THROW, 0]);
+        while !matches!(fight.player(1).state, PlayerState::Thrown(_)) {
+            fight.step([0, 0]);
+        }
+        assert_eq!(fight.player(0).animation.name(), "throw_hit");
+        fight.step([0, p2]);
+        fight
+    }
+
+    #[test]
+    fn a_throw_lands_once_the_tech_window_is_over() {
+        let mut fight = throw(0);
+        let health = fight.player(1).health;
+        wait_for(&mut fight, 1, PlayerState::Recoiling);
+        assert!(fight.player(1).health < health);
+    }
+
+    #[test]
+    fn pressing_throw_in_the_tech_window_escapes() {
+        let fight = throw(Input::THROW);
+        for p in &fight.playing().players {
+            assert_eq!(p.state, PlayerState::Recoiling);
+            assert_eq!(p.animation.name(), "throw_escape");
+            assert_eq!(p.health, fight.data.rules.max_health);
+        }
+    }
+
+    #[test]
+    fn a_throw_out_of_reach_whiffs() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.step([Input::THROW, 0]);
+        while fight.player(0).animation.name() == "throw" {
+            fight.step([0, 0]);
+        }
+        assert_eq!(fight.player(0).animation.name(), "throw_whiff");
+        assert_eq!(fight.player(1).state, PlayerState::Idle);
+    }
+
+    /// Steps, with player 2 holding `p2`, until player 1's attack connects.
+    fn until_connected(fight: &mut Fight, p2: u8) {
+        for _ in 0..60 {
+            let attacker = fight.player(0);
+            if attacker.animation.sprite().attack.is_some() && attacker.attack().is_none() {
+                return;
+            }
+            fight.step([0, p2]);
+        }
+        panic!("player 1's attack never connected");
+    }
+
+    /// Players close enough for player 1's light attack to connect, with
+    /// player 2 holding `p2`, stepped until it has.
+    fn light_attack_connected(p2: u8) -> Fight {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.player_mut(0).loc.x = Fixed::from_ratio(-1, 10);
+        fight.player_mut(1).loc.x = Fixed::from_ratio(1, 10);
+        fight.step([Input::LIGHT, p2]);
+        until_connected(&mut fight, p2);
+        fight
+    }
+
+    const QUARTER_CIRCLE_FORWARD: [u8; 3] = [
+        Input::DOWN,
+        Input::DOWN | Input::RIGHT,
+        Input::RIGHT | Input::LIGHT,
+    ];
+
+    #[test]
+    fn a_light_attack_that_hits_cancels_into_the_crouching_attack() {
+        let mut fight = light_attack_connected(0);
+        assert_eq!(fight.player(1).state, PlayerState::Recoiling);
+        fight.step([Input::DOWN | Input::LIGHT, 0]);
+        assert_eq!(fight.player(0).animation.name(), "crouch_attack");
+    }
+
+    #[test]
+    fn a_guarded_light_attack_only_cancels_into_a_special() {
+        // Crouching, so that holding back doesn't walk them out of reach.
+        let guard = Input::DOWN | Input::RIGHT;
+        let mut fight = light_attack_connected(guard);
+        assert_eq!(fight.player(1).state, PlayerState::Blocking);
+        let blocked = fight.state.clone();
+        fight.step([Input::DOWN | Input::LIGHT, guard]);
+        assert_eq!(fight.player(0).animation.name(), "attack");
+
+        fight.state = blocked;
+        for p1 in QUARTER_CIRCLE_FORWARD {
+            fight.step([p1, guard]);
+        }
+        assert_eq!(fight.player(0).animation.name(), "quarter_circle_attack");
+    }
+
+    #[test]
+    fn a_whiffed_attack_cant_be_cancelled() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.step([Input::LIGHT, 0]);
+        while fight.player(0).animation.sprite().attack.is_none() {
+            fight.step([0, 0]);
+        }
+        for p1 in QUARTER_CIRCLE_FORWARD {
+            fight.step([p1, 0]);
+        }
+        assert_eq!(fight.player(0).animation.name(), "attack");
+    }
+
+    #[test]
+    fn a_cancelled_hit_combos_with_scaled_damage() {
+        let mut fight = light_attack_connected(0);
+        assert_eq!(
+            fight.player(1).combo,
+            Combo {
+                hits: 1,
+                damage: 10,
+                valid: true
+            }
+        );
+        fight.step([Input::DOWN | Input::LIGHT, 0]);
+        until_connected(&mut fight, 0);
+        // The crouching attack's 8 damage, at 90% for being the second hit.
+        assert_eq!(
+            fight.player(1).combo,
+            Combo {
+                hits: 2,
+                damage: 10 + 7,
+                valid: true
+            }
+        );
+        assert_eq!(fight.player(1).health, 100 - 17);
+    }
+
+    #[test]
+    fn a_hit_after_hitstun_wears_off_in_the_air_isnt_a_true_combo() {
+        let juggle = AttackData {
+            damage: 10,
+            hitstun: 5,
+            blockstun: 5,
+            guard_break_stun: 120,
+            pushback: 0,
+            stamina_damage: 0,
+            hit: 0,
+        };
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.step([0, Input::JUMP]);
+        wait_for(&mut fight, 1, PlayerState::Airborne);
+        fight.hit(1, AttackHeight::Mid, &juggle);
+        wait_for(&mut fight, 1, PlayerState::Airborne);
+        fight.hit(1, AttackHeight::Mid, &juggle);
+        assert_eq!(
+            fight.player(1).combo,
+            Combo {
+                hits: 2,
+                damage: 10 + 9,
+                valid: false
+            }
+        );
+    }
+
+    #[test]
+    fn recovering_ends_the_combo() {
+        let mut fight = light_attack_connected(0);
+        wait_for(&mut fight, 1, PlayerState::Idle);
+        assert_eq!(fight.player(1).combo, Combo::default());
+        wait_for(&mut fight, 0, PlayerState::Idle);
+        fight.step([Input::LIGHT, 0]);
+        until_connected(&mut fight, 0);
+        assert_eq!(fight.player(1).combo.hits, 1);
+        assert_eq!(fight.player(1).health, 100 - 10 - 10);
+    }
+
+    /// Fires `count` of player 1's fireballs right on top of player 2.
+    fn fireballs_on_player_2(fight: &mut Fight, count: usize) {
+        let spawn = Spawn {
+            projectile: "fireball_projectile".to_string(),
+            offset: Vec2::ZERO,
+            speed: Fixed::ZERO,
+            lifetime: 60,
+        };
+        let projectile = Projectile::new(0, fight.player(1), &spawn, &fight.data.animations);
+        fight.playing_mut().projectiles = vec![projectile; count];
+    }
+
+    #[test]
+    fn projectiles_hit_once_a_frame() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fireballs_on_player_2(&mut fight, 2);
+        let data = &fight.data;
+        let Phase::Playing(playing) = &mut fight.state.phase else {
+            unreachable!()
+        };
+        playing.handle_projectiles([false, false], &data.rules, &data.animations);
+        assert_eq!(playing.players[1].combo.hits, 1);
+        assert_eq!(playing.projectiles.len(), 1);
+    }
+
+    #[test]
+    fn projectiles_skip_players_hit_in_melee_that_frame() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fireballs_on_player_2(&mut fight, 1);
+        let data = &fight.data;
+        let Phase::Playing(playing) = &mut fight.state.phase else {
+            unreachable!()
+        };
+        playing.handle_projectiles([false, true], &data.rules, &data.animations);
+        assert_eq!(playing.players[1].health, data.rules.max_health);
+        assert_eq!(playing.projectiles.len(), 1);
+
+        playing.handle_projectiles([false, false], &data.rules, &data.animations);
+        assert!(playing.players[1].health < data.rules.max_health);
+        assert!(playing.projectiles.is_empty());
+    }
+
+    #[test]
+    fn walking_moves_in_exact_fixed_point_steps() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.run([Input::RIGHT, Input::LEFT], 10);
+        assert_eq!(
+            fight.player(0).loc,
+            START_LOCATIONS[0] + Vec2::new(PLAYER_SPEED * 10, Fixed::ZERO)
+        );
+        assert_eq!(
+            fight.player(1).loc,
+            START_LOCATIONS[1] + Vec2::new(-PLAYER_SPEED * 10, Fixed::ZERO)
+        );
+    }
+
+    #[test]
+    fn mirrored_inputs_keep_the_players_exactly_mirrored() {
+        let mut fight = Fight::new(MatchSettings::default());
+        let script = [
+            (Input::RIGHT, Input::LEFT, 5),
+            (Input::JUMP | Input::LEFT, Input::JUMP | Input::RIGHT, 1),
+            (0, 0, 80),
+            (Input::RIGHT, Input::LEFT, 30),
+        ];
+        for (p1, p2, frames) in script {
+            for _ in 0..frames {
+                fight.step([p1, p2]);
+                let [a, b] = &fight.playing().players;
+                assert_eq!(a.loc, Vec2::new(-b.loc.x, b.loc.y));
+                assert_eq!(a.velocity, Vec2::new(-b.velocity.x, b.velocity.y));
+            }
+        }
+    }
+
+    /// Steps with no input until player `i` is in `state`, returning how
+    /// many frames that took.
+    fn wait_for(fight: &mut Fight, i: usize, state: PlayerState) -> u32 {
+        for frames in 1..=300 {
+            fight.step([0, 0]);
+            if fight.player(i).state == state {
+                return frames;
+            }
+        }
+        panic!("player {} never got to {state:?}", i + 1);
+    }
+
+    /// Jumps with `direction` held, and returns how long player 1 was in the
+    /// air for, and how far they went, once they've landed.
+    fn jump(fight: &mut Fight, direction: u8) -> (u32, Fixed) {
+        let start = fight.player(0).loc;
+        fight.step([Input::JUMP | direction, 0]);
+        while matches!(fight.player(0).state, PlayerState::JumpStartup(_)) {
+            assert_eq!(fight.player(0).loc, start);
+            fight.step([0, 0]);
+        }
+        let airtime = wait_for(fight, 0, PlayerState::Landing);
+        assert_eq!(fight.player(0).loc.y, Fixed::ZERO);
+        (airtime, fight.player(0).loc.x - start.x)
+    }
+
+    #[test]
+    fn the_first_matching_move_in_the_table_comes_out() {
+        for (input, name) in [
+            (Input::LIGHT, "attack"),
+            (Input::MEDIUM, "medium_attack"),
+            (Input::HEAVY, "heavy_attack"),
+            (Input::RIGHT | Input::HEAVY, "forward_heavy_attack"),
+            (Input::LEFT | Input::HEAVY, "heavy_attack"),
+            (Input::DOWN | Input::LIGHT, "crouch_attack"),
+        ] {
+            let mut fight = Fight::new(MatchSettings::default());
+            fight.step([input, 0]);
+            assert_eq!(fight.player(0).animation.name(), name, "{input:#b}");
+        }
+    }
+
+    #[test]
+    fn every_button_in_the_air_is_the_air_attack() {
+        for button in [Input::LIGHT, Input::MEDIUM, Input::HEAVY] {
+            let mut fight = Fight::new(MatchSettings::default());
+            fight.step([Input::JUMP, 0]);
+            while !fight.player(0).is_airborne() {
+                fight.step([0, 0]);
+            }
+            fight.step([button | Input::DOWN, 0]);
+            assert_eq!(
+                fight.player(0).animation.name(),
+                "air_attack",
+                "{button:#b}"
+            );
+        }
+    }
+
+    #[test]
+    fn jumps_start_on_the_ground_and_follow_a_fixed_arc() {
+        let mut fight = Fight::new(MatchSettings::default());
+        let (airtime, forwards) = jump(&mut fight, Input::RIGHT);
+        assert!(forwards > Fixed::ZERO);
+        wait_for(&mut fight, 0, PlayerState::Idle);
+        assert_eq!(jump(&mut fight, 0), (airtime, Fixed::ZERO));
+        wait_for(&mut fight, 0, PlayerState::Idle);
+        assert_eq!(jump(&mut fight, Input::LEFT), (airtime, -forwards));
+    }
+
+    #[test]
+    fn landing_recovery_cant_be_walked_out_of() {
+        let mut fight = Fight::new(MatchSettings::default());
+        jump(&mut fight, 0);
+        let landed = fight.player(0).loc;
+        loop {
+            fight.step([Input::RIGHT, 0]);
+            if fight.player(0).state != PlayerState::Landing {
+                break;
+            }
+            assert_eq!(fight.player(0).loc, landed);
+        }
+        assert_eq!(fight.player(0).state, PlayerState::Idle);
+        assert!(fight.player(0).loc.x > landed.x);
+    }
+
+    #[test]
+    fn landing_cuts_air_attacks_short_with_their_own_lag() {
+        let mut fight = Fight::new(MatchSettings::default());
+        jump(&mut fight, 0);
+        let landing_lag = wait_for(&mut fight, 0, PlayerState::Idle);
+
+        fight.step([Input::JUMP, 0]);
+        wait_for(&mut fight, 0, PlayerState::Airborne);
+        fight.run([0, 0], 20);
+        fight.step([Input::LIGHT, 0]);
+        assert_eq!(fight.player(0).animation.name(), "air_attack");
+        wait_for(&mut fight, 0, PlayerState::Landing);
+        assert_eq!(fight.player(0).animation.name(), "air_attack_landing");
+        assert!(wait_for(&mut fight, 0, PlayerState::Idle) > landing_lag);
+    }
+
+    /// Player 1 walks into player 2 for `frames`, checking that their
+    /// pushboxes are never left overlapping.
+    fn walk_into(fight: &mut Fight, frames: u32) {
+        for _ in 0..frames {
+            fight.step([Input::RIGHT, 0]);
+            let [a, b] = fight.playing().pushboxes();
+            assert_eq!(a.x_overlap(&b), None);
+        }
+    }
+
+    #[test]
+    fn walking_into_a_player_pushes_them_back() {
+        let mut fight = Fight::new(MatchSettings::default());
+        walk_into(&mut fight, 90);
+        assert!(fight.player(1).loc.x > START_LOCATIONS[1].x);
+    }
+
+    #[test]
+    fn a_cornered_player_stays_put_and_the_other_is_pushed_back() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.player_mut(0).loc.x = Fixed::from_ratio(1, 2);
+        fight.player_mut(1).loc.x = Fixed::ONE;
+        walk_into(&mut fight, 60);
+        assert_eq!(fight.player(1).loc.x, Fixed::ONE);
+        let [a, b] = fight.playing().pushboxes();
+        assert_eq!(a.max.x, b.min.x);
+    }
+
+    #[test]
+    fn walking_in_and_attacking_wins_the_match() {
+        let mut fight = Fight::new(MatchSettings::default());
+        for frame in 0..3000 {
+            if let Phase::MatchOver { winner, .. } = fight.state.phase {
+                assert_eq!(winner, Some(0));
+                assert_eq!(fight.state.wins, [2, 0]);
+                return;
+            }
+            let p1 = if frame % 60 < 40 {
+                Input::RIGHT
+            } else {
+                Input::LIGHT
+            };
+            fight.step([p1, 0]);
+        }
+        panic!("match still going: {:?}", fight.state.phase);
+    }
+}
@@ -0,0 +1,76 @@
+//! Which normal move each attack button does, depending on what the player is
+//! doing and which way they're holding.
+
+use serde::Deserialize;
+
+use crate::{
+    animation::{Animations, Stance},
+    command::{Button, RelDir},
+    player::Direction,
+    Input,
+};
+
+/// The normals of one character, loaded from `MOVES_FILE`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct MoveTable {
+    /// Checked in order, so entries with a direction go before the plain
+    /// entry for the same button and state.
+    pub normals: Vec<Normal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct Normal {
+    pub button: Button,
+    pub state: MoveState,
+    /// Direction that has to be held, relative to facing. Any, if not set.
+    #[serde(default)]
+    pub direction: Option<RelDir>,
+    /// Name of the animation played.
+    pub animation: String,
+}
+
+/// What the player is doing when they press the button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum MoveState {
+    Standing,
+    /// Holding down, on the ground.
+    Crouching,
+    Air,
+}
+
+impl MoveTable {
+    /// Normals usable in `state`, with whatever direction they need held in
+    /// `input`, in the order they're checked.
+    pub(crate) fn usable(
+        &self,
+        state: MoveState,
+        input: Input,
+        facing: Direction,
+    ) -> impl Iterator<Item = &Normal> {
+        self.normals.iter().filter(move |normal| {
+            normal.state == state
+                && normal
+                    .direction
+                    .is_none_or(|dir| dir.is_held(input, facing))
+        })
+    }
+
+    /// Panics if an entry plays an animation that doesn't exist, or plays a
+    /// ground animation in the air (or the other way around).
+    pub(crate) fn check(&self, anims: &Animations) {
+        for normal in &self.normals {
+            let data = anims
+                .get(&normal.animation)
+                .unwrap_or_else(|| panic!("missing normal animation {}", normal.animation));
+            let stance = match normal.state {
+                MoveState::Standing | MoveState::Crouching => Stance::Ground,
+                MoveState::Air => Stance::Air,
+            };
+            assert_eq!(
+                data.stance, stance,
+                "{}: wrong stance for a {:?} normal",
+                normal.animation, normal.state
+            );
+        }
+    }
+}
@@ -0,0 +1,737 @@
+use std::rc::Rc;
+
+use crate::{
+    animation::{
+        commands, AnimationData, Animations, AttackData, AttackHeight, Contact, MovePhase, Stance,
+        ThrowData,
+    },
+    combo::Combo,
+    data::Rules,
+    geom::{Aabb, Fixed, Vec2},
+    history::InputHistory,
+    moves::{MoveState, MoveTable},
+    Animation, Input,
+};
+
+pub const PLAYER_SPEED: Fixed = Fixed::from_ratio(1, 100);
+
+pub const JUMP_SPEED: Fixed = Fixed::from_ratio(5, 100);
+
+/// Horizontal speed of forward and back jumps, for their whole arc.
+pub const JUMP_X_SPEED: Fixed = Fixed::from_ratio(12, 1000);
+
+pub const GRAVITY: Fixed = Fixed::from_ratio(2, 1000);
+
+/// How much knockback slows down by every frame.
+pub const KNOCKBACK_FRICTION: Fixed = Fixed::from_ratio(1, 1000);
+
+/// Knockback both players get when a throw is escaped.
+pub const THROW_ESCAPE_PUSHBACK: Fixed = Fixed::from_ratio(15, 1000);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PlayerState {
+    Idle,
+    /// Can still act, like `Idle`, and guards low (and mid) attacks while
+    /// holding back.
+    Crouching,
+    Recoiling,
+    Blocking,
+    Attacking,
+    /// Playing the animation of a recognized command, such as a dash.
+    Command,
+    /// Still on the ground, about to jump.
+    JumpStartup(JumpArc),
+    /// Following a jump arc, until landing.
+    Airborne,
+    /// Recovering from a jump, on the ground.
+    Landing,
+    /// Grabbed, and about to take the throw's attack unless it's escaped.
+    Thrown(AttackData),
+    Death,
+}
+
+/// Being hit in the middle of a move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CounterHit {
+    /// During its startup or active frames.
+    Counter,
+    /// During its recovery, or anywhere in a move that can't hit, like a
+    /// dash or landing lag.
+    Punish,
+}
+
+/// Jumps follow a fixed arc, picked when jumping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JumpArc {
+    Neutral,
+    Forward,
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    East,
+    West,
+}
+
+#[derive(Debug, Clone, Hash)]
+pub struct Player {
+    pub facing: Direction,
+    pub loc: Vec2,
+    /// Knocked out at 0.
+    pub health: u32,
+    /// Spent on moves and guarding.
+    pub stamina: u32,
+    pub velocity: Vec2,
+    /// Speed the player is being pushed backwards at, by an attack.
+    pub knockback: Fixed,
+    /// Frames left in hitstun or blockstun. While this is nonzero it decides
+    /// when the current state ends, rather than the animation.
+    pub stun: u32,
+    /// Frames left in which the player has no hurtbox, after respawning.
+    pub invulnerable: u32,
+    /// Set while in the hitstun of a counter-hit, for the HUD.
+    pub counter_hit: Option<CounterHit>,
+    /// Hits taken in a row.
+    pub combo: Combo,
+
+    // Animation counts frames, and is authoratative
+    pub animation: Animation,
+    pub state: PlayerState,
+    pub inputs: InputHistory,
+}
+
+impl Player {
+    pub fn new(loc: Vec2, facing: Direction, rules: &Rules, anims: &Animations) -> Self {
+        Player {
+            facing,
+            animation: anims["standing"].to_anim(),
+            loc,
+            velocity: Vec2::ZERO,
+            knockback: Fixed::ZERO,
+            stun: 0,
+            invulnerable: 0,
+            counter_hit: None,
+            combo: Combo::default(),
+            state: PlayerState::Idle,
+            health: rules.max_health,
+            stamina: rules.max_stamina,
+            inputs: InputHistory::default(),
+        }
+    }
+
+    fn x_transform(&self) -> i32 {
+        match self.facing {
+            Direction::East => 1,
+            Direction::West => -1,
+        }
+    }
+
+    pub(crate) fn is_airborne(&self) -> bool {
+        self.loc.y > Fixed::ZERO || self.velocity.y > Fixed::ZERO
+    }
+
+    /// Sets the speed along the ground. In the air, the arc is fixed, so
+    /// this does nothing.
+    pub(crate) fn accelerate(&mut self, speed: Fixed) {
+        if !self.is_airborne() {
+            self.velocity.x = speed * self.x_transform();
+        }
+    }
+
+    /// Moves by a frame of velocity, under gravity, and lands.
+    pub(crate) fn update_loc(&mut self, anims: &Animations) {
+        if self.is_airborne() {
+            self.velocity.y -= GRAVITY;
+        }
+        self.loc += self.velocity;
+        self.loc.x -= self.knockback * self.x_transform();
+        self.knockback = (self.knockback - KNOCKBACK_FRICTION).max(Fixed::ZERO);
+        self.loc.x = self.loc.x.clamp(-Fixed::ONE, Fixed::ONE);
+        if self.loc.y < Fixed::ZERO {
+            self.loc.y = Fixed::ZERO;
+            self.velocity = Vec2::ZERO;
+            if self.animation.stance() == Stance::Air {
+                self.start_landing(anims);
+            }
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.loc
+    }
+
+    pub fn hitbox(&self) -> Option<Aabb> {
+        let hb = self.animation.sprite().hitbox?;
+        Some(self.to_world(hb))
+    }
+
+    pub fn hurtbox(&self) -> Option<Aabb> {
+        if self.invulnerable > 0 {
+            return None;
+        }
+        let hb = self.animation.sprite().hurtbox?;
+        Some(self.to_world(hb))
+    }
+
+    /// What the current hitbox does, if there is one and it hasn't already
+    /// connected.
+    pub fn attack(&self) -> Option<AttackData> {
+        self.animation.unlanded_attack()
+    }
+
+    pub fn pushbox(&self) -> Aabb {
+        self.to_world(self.animation.sprite().pushbox)
+    }
+
+    pub fn grabbox(&self) -> Option<Aabb> {
+        let gb = self.animation.sprite().grabbox?;
+        Some(self.to_world(gb))
+    }
+
+    /// Whether a grabbox touching our hurtbox would catch us.
+    pub(crate) fn is_throwable(&self) -> bool {
+        !self.is_airborne() && self.stun == 0 && !matches!(self.state, PlayerState::Thrown(_))
+    }
+
+    /// Player frame of reference to world.
+    fn to_world(&self, mut b: Aabb) -> Aabb {
+        if matches!(self.facing, Direction::West) {
+            b = b.reflect_x();
+        }
+        b.translate(self.center())
+    }
+
+    /// Whether there's enough stamina to start `data` as a move.
+    pub(crate) fn can_afford(&self, data: &AnimationData) -> bool {
+        self.stamina >= data.stamina_cost
+    }
+
+    /// Plays `data` as a move, paying its stamina cost.
+    fn start_move(&mut self, state: PlayerState, data: &Rc<AnimationData>) {
+        self.stamina -= data.stamina_cost;
+        self.state = state;
+        self.animation = data.to_anim();
+    }
+
+    pub(crate) fn start_attack(&mut self, data: &Rc<AnimationData>) {
+        self.start_move(PlayerState::Attacking, data);
+    }
+
+    /// Starts the first affordable command usable from `stance` found in the
+    /// input history, if any.
+    pub(crate) fn try_command(
+        &mut self,
+        stance: Stance,
+        buffer_frames: u32,
+        anims: &Animations,
+    ) -> bool {
+        for (data, command) in commands(anims, stance) {
+            if self.can_afford(data) && command.take(&mut self.inputs, self.facing, buffer_frames) {
+                self.start_move(PlayerState::Command, data);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn move_state(&self) -> MoveState {
+        let input = self.inputs.get(0);
+        if self.is_airborne() {
+            MoveState::Air
+        } else if input.is_down_pressed() && !input.is_jump_pressed() {
+            MoveState::Crouching
+        } else {
+            MoveState::Standing
+        }
+    }
+
+    /// Starts the first normal in `moves` for what the player's doing whose
+    /// button was pressed, if any.
+    pub(crate) fn try_normal(
+        &mut self,
+        moves: &MoveTable,
+        buffer_frames: u32,
+        anims: &Animations,
+    ) -> bool {
+        self.try_normal_where(moves, buffer_frames, anims, |_| true)
+    }
+
+    /// `try_normal`, only considering normals whose animation is `allowed`.
+    fn try_normal_where(
+        &mut self,
+        moves: &MoveTable,
+        buffer_frames: u32,
+        anims: &Animations,
+        allowed: impl Fn(&str) -> bool,
+    ) -> bool {
+        let usable = moves.usable(self.move_state(), self.inputs.get(0), self.facing);
+        for normal in usable {
+            let data = &anims[&normal.animation];
+            if allowed(&normal.animation)
+                && self.can_afford(data)
+                && self.inputs.take_press(normal.button.bit(), buffer_frames)
+            {
+                self.start_attack(data);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Cuts the current move short with one of those its sprite can cancel
+    /// into, if that move's input is there, be it a command or a normal.
+    pub(crate) fn try_cancel(
+        &mut self,
+        moves: &MoveTable,
+        buffer_frames: u32,
+        anims: &Animations,
+    ) -> bool {
+        let cancels = self.animation.cancels();
+        if cancels.is_empty() {
+            return false;
+        }
+
+        for (data, command) in commands(anims, self.animation.stance()) {
+            if cancels.iter().any(|name| data.is_named(name))
+                && self.can_afford(data)
+                && command.take(&mut self.inputs, self.facing, buffer_frames)
+            {
+                self.start_move(PlayerState::Command, data);
+                return true;
+            }
+        }
+
+        self.try_normal_where(moves, buffer_frames, anims, |name| {
+            cancels.iter().any(|cancel| cancel == name)
+        })
+    }
+
+    /// Back to being able to act, which ends any combo.
+    pub(crate) fn start_idle(&mut self) {
+        self.state = PlayerState::Idle;
+        self.combo = Combo::default();
+    }
+
+    pub(crate) fn start_jump(&mut self, arc: JumpArc, anims: &Animations) {
+        self.state = PlayerState::JumpStartup(arc);
+        self.animation = anims["jump_startup"].to_anim();
+    }
+
+    /// Leaves the ground, at the end of jump startup.
+    pub(crate) fn take_off(&mut self, arc: JumpArc, anims: &Animations) {
+        let x_speed = match arc {
+            JumpArc::Neutral => Fixed::ZERO,
+            JumpArc::Forward => JUMP_X_SPEED,
+            JumpArc::Back => -JUMP_X_SPEED,
+        };
+        self.velocity = Vec2::new(x_speed * self.x_transform(), JUMP_SPEED);
+        self.state = PlayerState::Airborne;
+        self.animation = anims["jump"].to_anim();
+    }
+
+    /// Back in the air state after, say, being hit mid-jump. There's no
+    /// control over the arc either way.
+    pub(crate) fn start_falling(&mut self, anims: &Animations) {
+        self.state = PlayerState::Airborne;
+        self.animation = anims["jump"].to_anim();
+    }
+
+    /// The grab connected, follow through.
+    pub(crate) fn start_throw_success(&mut self, throw: &ThrowData, anims: &Animations) {
+        self.animation = anims[&throw.success].to_anim();
+    }
+
+    /// The throw animation ended without grabbing anyone.
+    pub(crate) fn start_throw_whiff(&mut self, anims: &Animations) {
+        let whiff = &self.animation.throw().unwrap().whiff;
+        self.animation = anims[whiff].to_anim();
+    }
+
+    /// Caught by `throw`. Lasts until the tech window is over.
+    pub(crate) fn start_thrown(&mut self, throw: &ThrowData, anims: &Animations) {
+        self.state = PlayerState::Thrown(throw.attack);
+        self.animation = anims["thrown"].to_anim();
+        self.stun = throw.tech_window;
+        self.knockback = Fixed::ZERO;
+    }
+
+    /// For both players, when a throw is escaped.
+    pub(crate) fn start_throw_escape(&mut self, anims: &Animations) {
+        self.state = PlayerState::Recoiling;
+        self.animation = anims["throw_escape"].to_anim();
+        self.stun = 0;
+        self.knockback = THROW_ESCAPE_PUSHBACK;
+    }
+
+    /// Cuts whatever was playing in the air short.
+    fn start_landing(&mut self, anims: &Animations) {
+        self.state = PlayerState::Landing;
+        self.animation = anims[self.animation.landing()].to_anim();
+    }
+
+    pub(crate) fn ensure_standing(&mut self, anims: &Animations) {
+        self.state = PlayerState::Idle;
+        if !self.animation.is_instance(&anims["standing"]) {
+            self.animation = anims["standing"].to_anim();
+        }
+    }
+
+    pub(crate) fn ensure_walking_forwards(&mut self, anims: &Animations) {
+        self.state = PlayerState::Idle;
+        if !self.animation.is_instance(&anims["forward"]) {
+            self.animation = anims["forward"].to_anim();
+        }
+    }
+
+    pub(crate) fn ensure_walking_backwards(&mut self, anims: &Animations) {
+        self.state = PlayerState::Idle;
+        if !self.animation.is_instance(&anims["backward"]) {
+            self.animation = anims["backward"].to_anim();
+        }
+    }
+
+    pub(crate) fn ensure_crouching(&mut self, anims: &Animations) {
+        self.state = PlayerState::Crouching;
+        if !self.animation.is_instance(&anims["crouch"]) {
+            self.animation = anims["crouch"].to_anim();
+        }
+    }
+
+    fn is_walking_backwards(&self, anims: &Animations) -> bool {
+        self.animation.is_instance(&anims["backward"])
+    }
+
+    /// Loses a frame's worth of stamina to decay. While free to act, also
+    /// regains some, or loses more while backing off.
+    pub(crate) fn update_stamina(&mut self, rules: &Rules, anims: &Animations) {
+        if matches!(self.state, PlayerState::Idle | PlayerState::Crouching) {
+            if self.is_walking_backwards(anims) {
+                self.stamina = self.stamina.saturating_sub(rules.backwards_stamina_drain);
+            } else {
+                self.stamina = (self.stamina + rules.stamina_regen).min(rules.max_stamina);
+            }
+        }
+        self.stamina = self.stamina.saturating_sub(rules.stamina_decay);
+    }
+
+    fn is_holding_back(&self) -> bool {
+        let back = match self.facing {
+            Direction::East => Input::LEFT,
+            Direction::West => Input::RIGHT,
+        };
+        self.inputs.get(0).input_bits & back != 0
+    }
+
+    fn knock_back(&mut self, attack: &AttackData) {
+        self.knockback = Fixed::from_ratio(attack.pushback, 1000);
+    }
+
+    fn start_recoil(&mut self, attack: &AttackData, anims: &Animations) {
+        self.state = PlayerState::Recoiling;
+        self.animation = anims["recoil"].to_anim();
+        self.stun = attack.hitstun;
+        self.knock_back(attack);
+    }
+
+    fn start_block(&mut self, crouching: bool, attack: &AttackData, anims: &Animations) {
+        self.state = PlayerState::Blocking;
+        let name = if crouching { "crouch_block" } else { "block" };
+        self.animation = anims[name].to_anim();
+        self.stun = attack.blockstun;
+        self.knock_back(attack);
+    }
+
+    fn start_death(&mut self, anims: &Animations) {
+        self.state = PlayerState::Death;
+        self.animation = anims["death"].to_anim();
+        self.stun = 0;
+    }
+
+    fn start_guard_broken(&mut self, attack: &AttackData, anims: &Animations) {
+        self.state = PlayerState::Recoiling;
+        self.animation = anims["guard_broken"].to_anim();
+        self.stun = attack.guard_break_stun;
+        self.knock_back(attack);
+    }
+
+    /// What being hit right now would count as, if the player is in the
+    /// middle of a move.
+    fn counter_hit_now(&self) -> Option<CounterHit> {
+        if !matches!(
+            self.state,
+            PlayerState::Attacking | PlayerState::Command | PlayerState::Landing
+        ) {
+            return None;
+        }
+        // Moves that never hit, like whiffed throws, are all recovery.
+        match self.animation.phase() {
+            Some(MovePhase::Startup | MovePhase::Active) => Some(CounterHit::Counter),
+            Some(MovePhase::Recovery) | None => Some(CounterHit::Punish),
+        }
+    }
+
+    /// Takes an attack that wasn't guarded, with its counter-hit bonus if
+    /// it interrupted a move and scaled down by the combo so far, and
+    /// recoils from it, unless it was a knockout.
+    pub(crate) fn take_hit(&mut self, attack: &AttackData, rules: &Rules, anims: &Animations) {
+        self.counter_hit = self.counter_hit_now();
+        let bonus = self.counter_hit.map(|counter| rules.counter_bonus(counter));
+        let attack = &bonus.map_or(*attack, |bonus| bonus.apply(attack));
+        let damage = self.combo.scale(attack.damage, rules);
+        let in_hitstun = self.state == PlayerState::Recoiling && self.stun > 0;
+        self.combo.add_hit(damage, in_hitstun);
+        self.health = self.health.saturating_sub(damage);
+        self.stamina = self
+            .stamina
+            .saturating_sub(attack.stamina_damage + rules.hit_stamina_loss);
+        if self.health == 0 {
+            self.start_death(anims);
+        } else {
+            self.start_recoil(attack, anims);
+        }
+    }
+
+    pub(crate) fn handle_hit(
+        &mut self,
+        height: AttackHeight,
+        attack: &AttackData,
+        rules: &Rules,
+        anims: &Animations,
+    ) -> Contact {
+        // Still guarding from blockstun, as long as back is held, high or low
+        // by whether down is too.
+        let still_blocking = self.state == PlayerState::Blocking && self.is_holding_back();
+        let down = self.inputs.get(0).is_down_pressed();
+        let standing_guard = self.is_walking_backwards(anims) || (still_blocking && !down);
+        let crouching_guard = (self.state == PlayerState::Crouching && self.is_holding_back())
+            || (still_blocking && down);
+        let guarded = match height {
+            AttackHeight::High => standing_guard,
+            AttackHeight::Mid => standing_guard || crouching_guard,
+            AttackHeight::Low => crouching_guard,
+        };
+
+        if !guarded {
+            self.take_hit(attack, rules, anims);
+            Contact::Hit
+        } else if self.stamina < attack.stamina_damage {
+            // Too tired to hold it.
+            self.stamina = 0;
+            self.start_guard_broken(attack, anims);
+            Contact::Hit
+        } else {
+            self.stamina -= attack.stamina_damage;
+            self.start_block(crouching_guard, attack, anims);
+            Contact::Block
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::Fight, MatchSettings};
+
+    const JAB: AttackData = AttackData {
+        damage: 10,
+        hitstun: 30,
+        blockstun: 20,
+        guard_break_stun: 120,
+        pushback: 0,
+        stamina_damage: 50,
+        hit: 0,
+    };
+
+    /// Long enough for player 1 to recover and attack again before it ends.
+    const LONG_BLOCKSTUN: AttackData = AttackData {
+        blockstun: 120,
+        ..JAB
+    };
+
+    /// Players close enough for any normal to reach, with player 2 holding
+    /// back (right, since they face west) and `extra`.
+    fn guarding(extra: u8) -> Fight {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.player_mut(0).loc.x = Fixed::from_ratio(-1, 10);
+        fight.player_mut(1).loc.x = Fixed::from_ratio(1, 10);
+        fight.run([0, Input::RIGHT | extra], 5);
+        fight
+    }
+
+    /// Puts player 2 in blockstun, as if they'd just guarded an attack.
+    fn in_blockstun(fight: &mut Fight, crouching: bool) {
+        let anims = fight.data.animations.clone();
+        fight
+            .player_mut(1)
+            .start_block(crouching, &LONG_BLOCKSTUN, &anims);
+    }
+
+    /// Steps with `inputs` held until player 1's attack connects, and returns
+    /// how player 2 took it.
+    fn connect(fight: &mut Fight, inputs: [u8; 2]) -> Contact {
+        for _ in 0..60 {
+            fight.step(inputs);
+            let attacker = fight.player(0);
+            if attacker.animation.sprite().attack.is_some() && attacker.attack().is_none() {
+                return match fight.player(1).state {
+                    PlayerState::Blocking => Contact::Block,
+                    PlayerState::Recoiling => Contact::Hit,
+                    ref state => panic!("connected with player 2 {state:?}"),
+                };
+            }
+        }
+        panic!("player 1's attack never connected");
+    }
+
+    #[test]
+    fn crouching_guards_lows() {
+        let mut fight = guarding(Input::DOWN);
+        let contact = connect(
+            &mut fight,
+            [Input::DOWN | Input::LIGHT, Input::RIGHT | Input::DOWN],
+        );
+        assert_eq!(contact, Contact::Block);
+        assert_eq!(fight.player(1).animation.name(), "crouch_block");
+    }
+
+    #[test]
+    fn an_overhead_hits_a_crouch_blocker() {
+        let mut fight = guarding(Input::DOWN);
+        in_blockstun(&mut fight, true);
+        let overhead = Input::RIGHT | Input::HEAVY;
+        let contact = connect(&mut fight, [overhead, Input::RIGHT | Input::DOWN]);
+        assert_eq!(fight.player(0).animation.name(), "forward_heavy_attack");
+        assert_eq!(contact, Contact::Hit);
+    }
+
+    #[test]
+    fn blockstun_keeps_guarding_while_back_is_held() {
+        let mut fight = guarding(0);
+        in_blockstun(&mut fight, false);
+        let contact = connect(&mut fight, [Input::LIGHT, Input::RIGHT]);
+        assert_eq!(contact, Contact::Block);
+        assert_eq!(fight.player(1).health, 100);
+        assert_eq!(fight.player(1).animation.name(), "block");
+    }
+
+    #[test]
+    fn blockstun_keeps_crouch_guarding_while_down_back_is_held() {
+        let mut fight = guarding(Input::DOWN);
+        in_blockstun(&mut fight, true);
+        let low = Input::DOWN | Input::LIGHT;
+        let contact = connect(&mut fight, [low, Input::RIGHT | Input::DOWN]);
+        assert_eq!(contact, Contact::Block);
+        assert_eq!(fight.player(1).animation.name(), "crouch_block");
+    }
+
+    #[test]
+    fn stamina_decays_outside_of_rest() {
+        let mut fight = Fight::new(MatchSettings::default());
+        let rules = fight.data.rules.clone();
+        assert_eq!(fight.hit(1, AttackHeight::Mid, &JAB), Contact::Hit);
+        let stamina = fight.player(1).stamina;
+        fight.run([0, 0], 10);
+        assert_eq!(fight.player(1).state, PlayerState::Recoiling);
+        assert_eq!(fight.player(1).stamina, stamina - 10 * rules.stamina_decay);
+    }
+
+    #[test]
+    fn stamina_regenerates_at_rest() {
+        let mut fight = Fight::new(MatchSettings::default());
+        let rules = fight.data.rules.clone();
+        fight.player_mut(1).stamina = 0;
+        fight.run([0, 0], 10);
+        let net = rules.stamina_regen - rules.stamina_decay;
+        assert_eq!(fight.player(1).stamina, 10 * net);
+    }
+
+    #[test]
+    fn letting_go_of_back_in_blockstun_drops_the_guard() {
+        let mut fight = guarding(0);
+        in_blockstun(&mut fight, false);
+        assert_eq!(connect(&mut fight, [Input::LIGHT, 0]), Contact::Hit);
+        assert!(fight.player(1).health < 100);
+    }
+
+    /// Hits player 2 with `JAB`, returning the damage it did and what kind of
+    /// counter-hit it was.
+    fn jab(fight: &mut Fight) -> (u32, Option<CounterHit>) {
+        let health = fight.player(1).health;
+        assert_eq!(fight.hit(1, AttackHeight::Mid, &JAB), Contact::Hit);
+        let player = fight.player(1);
+        (health - player.health, player.counter_hit)
+    }
+
+    #[test]
+    fn hits_in_neutral_are_not_counter_hits() {
+        let mut fight = Fight::new(MatchSettings::default());
+        assert_eq!(jab(&mut fight), (10, None));
+        assert_eq!(fight.player(1).stun, 30);
+    }
+
+    #[test]
+    fn hits_during_startup_are_counter_hits() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.step([0, Input::HEAVY]);
+        assert_eq!(fight.player(1).animation.phase(), Some(MovePhase::Startup));
+        assert_eq!(jab(&mut fight), (12, Some(CounterHit::Counter)));
+        assert_eq!(fight.player(1).stun, 30 + 8);
+    }
+
+    #[test]
+    fn hits_during_recovery_are_punishes() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.step([0, Input::LIGHT]);
+        while fight.player(1).animation.phase() != Some(MovePhase::Recovery) {
+            fight.step([0, 0]);
+        }
+        assert_eq!(jab(&mut fight), (13, Some(CounterHit::Punish)));
+        assert_eq!(fight.player(1).stun, 30 + 15);
+    }
+
+    #[test]
+    fn a_whiffed_throw_is_punished() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.step([0, Input::THROW]);
+        while fight.player(1).animation.name() == "throw" {
+            fight.step([0, 0]);
+        }
+        assert_eq!(fight.player(1).animation.name(), "throw_whiff");
+        assert_eq!(jab(&mut fight), (13, Some(CounterHit::Punish)));
+    }
+
+    #[test]
+    fn a_dash_is_punished() {
+        let mut fight = Fight::new(MatchSettings::default());
+        // Forwards is left for player 2.
+        for p2 in [Input::LEFT, 0, Input::LEFT] {
+            fight.step([0, p2]);
+        }
+        assert_eq!(fight.player(1).animation.name(), "forward_dash");
+        assert_eq!(jab(&mut fight), (13, Some(CounterHit::Punish)));
+    }
+
+    #[test]
+    fn landing_lag_is_punished() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.step([0, Input::JUMP]);
+        while fight.player(1).state != PlayerState::Landing {
+            fight.step([0, 0]);
+        }
+        assert_eq!(jab(&mut fight), (13, Some(CounterHit::Punish)));
+    }
+
+    #[test]
+    fn a_light_attack_counters_a_slower_heavy_attack() {
+        let mut fight = Fight::new(MatchSettings::default());
+        fight.player_mut(0).loc.x = Fixed::from_ratio(-1, 10);
+        fight.player_mut(1).loc.x = Fixed::from_ratio(1, 10);
+        fight.step([Input::LIGHT, 0]);
+        fight.run([0, 0], 9);
+        fight.step([0, Input::HEAVY]);
+        assert_eq!(connect(&mut fight, [0, 0]), Contact::Hit);
+        assert_eq!(fight.player(1).counter_hit, Some(CounterHit::Counter));
+        assert_eq!(fight.player(1).health, 100 - 12);
+    }
+}
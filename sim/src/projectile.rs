@@ -0,0 +1,75 @@
+use crate::{
+    animation::{Animations, AttackData, AttackHeight, Spawn},
+    geom::{Aabb, Fixed, Vec2},
+    player::{Direction, Player},
+    Animation,
+};
+
+/// How far past the edge of the stage a projectile can be and still hit,
+/// so that it doesn't vanish before reaching a cornered player.
+const STAGE_MARGIN: Fixed = Fixed::from_ratio(1, 5);
+
+/// Something fired by a player's move, which flies straight on until it hits
+/// the other player, meets one of theirs, or runs out of lifetime.
+#[derive(Debug, Clone, Hash)]
+pub struct Projectile {
+    /// Index of the player who fired it. It can't hit them.
+    pub owner: usize,
+    pub facing: Direction,
+    pub loc: Vec2,
+    pub velocity: Vec2,
+    pub frames_left: u32,
+    pub animation: Animation,
+}
+
+impl Projectile {
+    pub(crate) fn new(owner: usize, player: &Player, spawn: &Spawn, anims: &Animations) -> Self {
+        let sign = match player.facing {
+            Direction::East => 1,
+            Direction::West => -1,
+        };
+        Projectile {
+            owner,
+            facing: player.facing,
+            loc: player.loc
+                + Vec2 {
+                    x: spawn.offset.x * sign,
+                    y: spawn.offset.y,
+                },
+            velocity: Vec2 {
+                x: spawn.speed * sign,
+                y: Fixed::ZERO,
+            },
+            frames_left: spawn.lifetime,
+            animation: anims[&spawn.projectile].to_anim(),
+        }
+    }
+
+    /// Moves by a frame. Returns whether the projectile is still in flight,
+    /// rather than fizzled out or off the stage.
+    pub(crate) fn update(&mut self) -> bool {
+        let _ = self.animation.next_frame();
+        self.loc += self.velocity;
+        self.frames_left = self.frames_left.saturating_sub(1);
+        let on_stage =
+            -Fixed::ONE - STAGE_MARGIN <= self.loc.x && self.loc.x <= Fixed::ONE + STAGE_MARGIN;
+        self.frames_left > 0 && on_stage
+    }
+
+    pub fn hitbox(&self) -> Option<Aabb> {
+        let mut hb = self.animation.sprite().hitbox?;
+        if matches!(self.facing, Direction::West) {
+            hb = hb.reflect_x();
+        }
+        Some(hb.translate(self.loc))
+    }
+
+    /// What the current hitbox does, if there is one.
+    pub fn attack(&self) -> Option<AttackData> {
+        self.animation.unlanded_attack()
+    }
+
+    pub fn height(&self) -> AttackHeight {
+        self.animation.height()
+    }
+}
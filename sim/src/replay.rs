@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{game_data_hash, GameData, GameState, Input, MatchSettings};
+
+/// Bumped whenever the format, or the meaning of the recorded inputs, changes.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Everything needed to re-simulate a game: the data it was played with, and
+/// the inputs of both players on every frame. The start state isn't stored,
+/// since it follows from the data and the settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// `game_data_hash` of the data the game was played with.
+    pub data_hash: u64,
+    pub settings: MatchSettings,
+    /// Indexed by frame.
+    pub inputs: Vec<[Input; 2]>,
+}
+
+impl Replay {
+    pub fn new(data: &GameData, start: &GameState) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            data_hash: game_data_hash(data),
+            settings: start.settings,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Records the inputs `frame` was simulated with.
+    ///
+    /// After a rollback, frames are simulated again with corrected inputs, so
+    /// this forgets everything recorded from `frame` onwards.
+    pub fn record(&mut self, frame: usize, inputs: [Input; 2]) {
+        self.inputs.truncate(frame);
+        assert_eq!(self.inputs.len(), frame, "frames recorded out of order");
+        self.inputs.push(inputs);
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::to_string(self).unwrap()
+    }
+
+    /// Parses a replay, and checks that it can be played back with `data`.
+    pub fn from_ron(s: &str, data: &GameData) -> Result<Self, String> {
+        let replay: Replay = ron::from_str(s).map_err(|e| e.to_string())?;
+        if replay.version != REPLAY_VERSION {
+            return Err(format!(
+                "replay is version {}, expected {REPLAY_VERSION}",
+                replay.version
+            ));
+        }
+        replay
+            .settings
+            .validate()
+            .map_err(|e| format!("replay has invalid settings: {e}"))?;
+        if replay.data_hash != game_data_hash(data) {
+            return Err("replay was recorded with different game data".into());
+        }
+        Ok(replay)
+    }
+
+    pub fn start_state(&self, data: &GameData) -> GameState {
+        GameState::new(self.settings, data).expect("replay settings are checked when loading")
+    }
+
+    /// Simulates the whole replay, headless.
+    pub fn play(&self, data: &GameData) -> GameState {
+        let mut state = self.start_state(data);
+        for &inputs in &self.inputs {
+            state.advance_frame(inputs, data);
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn recorded(data: &GameData) -> (Replay, GameState) {
+        let mut state = GameState::new(MatchSettings::default(), data).unwrap();
+        let mut replay = Replay::new(data, &state);
+        for (frame, inputs) in testing::mash(3, 2000).into_iter().enumerate() {
+            replay.record(frame, inputs);
+            state.advance_frame(inputs, data);
+        }
+        (replay, state)
+    }
+
+    #[test]
+    fn plays_back_to_the_same_state_through_ron() {
+        let data = testing::game_data();
+        let (replay, live) = recorded(&data);
+        let loaded = Replay::from_ron(&replay.to_ron(), &data).unwrap();
+        assert_eq!(loaded.inputs, replay.inputs);
+        assert_eq!(loaded.play(&data).checksum(), live.checksum());
+    }
+
+    #[test]
+    fn rerecording_after_a_rollback_replaces_later_frames() {
+        let data = testing::game_data();
+        let (mut replay, live) = recorded(&data);
+        let tail = replay.inputs[1500..].to_vec();
+        replay.record(1500, [Input::default(); 2]);
+        assert_eq!(replay.inputs.len(), 1501);
+        for (frame, inputs) in (1500..).zip(tail) {
+            replay.record(frame, inputs);
+        }
+        assert_eq!(replay.play(&data).checksum(), live.checksum());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let data = testing::game_data();
+        let (mut replay, _) = recorded(&data);
+        replay.version -= 1;
+        assert!(Replay::from_ron(&replay.to_ron(), &data).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let data = testing::game_data();
+        let (mut replay, _) = recorded(&data);
+        replay.settings.stocks = 0;
+        assert!(Replay::from_ron(&replay.to_ron(), &data).is_err());
+    }
+}
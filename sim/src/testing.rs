@@ -0,0 +1,103 @@
+//! Drives fights headlessly with the real assets, for tests.
+
+use std::path::Path;
+
+use crate::{
+    animation::{AttackData, AttackHeight, Contact},
+    load_game_data_from_dir, GameData, GameState, Input, MatchSettings, Phase, Player,
+    PlayingState, COUNTDOWN_FRAMES,
+};
+
+/// Everything in `assets/`, loaded the way the game does.
+pub(crate) fn game_data() -> GameData {
+    load_game_data_from_dir(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../assets")))
+}
+
+/// `frames` frames of both players mashing, the same for every `seed`, to
+/// reach as much of the simulation as possible.
+pub(crate) fn mash(seed: u64, frames: usize) -> Vec<[Input; 2]> {
+    // xorshift64
+    let mut x = seed | 1;
+    let mut next = move || {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        x
+    };
+    let mut held = [Input::default(); 2];
+    (0..frames)
+        .map(|frame| {
+            // Hold each input for a while, so that moves get to come out.
+            if frame % 8 == 0 {
+                let bits = next();
+                held = [0, 1].map(|i| Input {
+                    input_bits: (bits >> (8 * i)) as u8,
+                });
+            }
+            held
+        })
+        .collect()
+}
+
+/// A match, stepped a frame at a time from raw input bits.
+pub(crate) struct Fight {
+    pub state: GameState,
+    pub data: GameData,
+}
+
+impl Fight {
+    /// Starts on the first frame the players can act.
+    pub fn new(settings: MatchSettings) -> Self {
+        let data = game_data();
+        let mut fight = Fight {
+            state: GameState::new(settings, &data).unwrap(),
+            data,
+        };
+        fight.run([0, 0], COUNTDOWN_FRAMES);
+        fight
+    }
+
+    pub fn step(&mut self, inputs: [u8; 2]) {
+        let inputs = inputs.map(|input_bits| Input { input_bits });
+        self.state.advance_frame(inputs, &self.data);
+    }
+
+    /// Holds `inputs` for `frames` frames.
+    pub fn run(&mut self, inputs: [u8; 2], frames: u32) {
+        for _ in 0..frames {
+            self.step(inputs);
+        }
+    }
+
+    /// Panics outside of a round.
+    pub fn playing(&self) -> &PlayingState {
+        match &self.state.phase {
+            Phase::Playing(playing) => playing,
+            phase => panic!("not playing: {phase:?}"),
+        }
+    }
+
+    pub fn player(&self, i: usize) -> &Player {
+        &self.playing().players[i]
+    }
+
+    pub fn playing_mut(&mut self) -> &mut PlayingState {
+        match &mut self.state.phase {
+            Phase::Playing(playing) => playing,
+            phase => panic!("not playing: {phase:?}"),
+        }
+    }
+
+    pub fn player_mut(&mut self, i: usize) -> &mut Player {
+        &mut self.playing_mut().players[i]
+    }
+
+    /// Hits player `i` with `attack` right now, wherever the players are.
+    pub fn hit(&mut self, i: usize, height: AttackHeight, attack: &AttackData) -> Contact {
+        let Phase::Playing(playing) = &mut self.state.phase else {
+            panic!("not playing: {:?}", self.state.phase);
+        };
+        let data = &self.data;
+        playing.players[i].handle_hit(height, attack, &data.rules, &data.animations)
+    }
+}
@@ -0,0 +1,83 @@
+//! Settings kept between runs.
+
+#[cfg(target_arch = "wasm32")]
+use comfy::error;
+use serde::{Deserialize, Serialize};
+
+use crate::input::{Bindings, SocdMode};
+
+#[cfg(not(target_arch = "wasm32"))]
+const CONFIG_PATH: &str = "config.ron";
+
+#[cfg(target_arch = "wasm32")]
+const CONFIG_KEY: &str = "fungus_fighter_config";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Indexed by local player, in handle order.
+    pub bindings: [Bindings; 2],
+    /// Applies to every local player.
+    pub socd: SocdMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bindings: [Bindings::default_for(0), Bindings::default_for(1)],
+            socd: SocdMode::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Falls back to the defaults if there is no config yet, or it can't be
+    /// read.
+    pub fn load() -> Self {
+        let Some(contents) = read() else {
+            return Config::default();
+        };
+        match ron::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("Ignoring unreadable config: {e}");
+                Config::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap();
+        write(&contents);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read() -> Option<String> {
+    std::fs::read_to_string(CONFIG_PATH).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(contents: &str) {
+    if let Err(e) = std::fs::write(CONFIG_PATH, contents) {
+        println!("Failed to save config to {CONFIG_PATH}: {e}");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read() -> Option<String> {
+    local_storage()?.get_item(CONFIG_KEY).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(contents: &str) {
+    let saved = local_storage().map(|storage| storage.set_item(CONFIG_KEY, contents));
+    if !matches!(saved, Some(Ok(()))) {
+        error!("Failed to save config to local storage");
+    }
+}
@@ -0,0 +1,103 @@
+//! The screen for rebinding keys.
+
+use std::ops::ControlFlow;
+
+use comfy::*;
+
+use crate::{
+    config::Config,
+    input::{key_name, Action, SocdMode, KEYS},
+};
+
+pub struct ControlsMenu {
+    config: Config,
+    /// The binding waiting for a key press, by local player.
+    rebinding: Option<(usize, Action)>,
+}
+
+impl ControlsMenu {
+    pub fn new() -> Self {
+        ControlsMenu {
+            config: Config::load(),
+            rebinding: None,
+        }
+    }
+
+    /// Breaks when the menu should be closed.
+    pub fn update(&mut self) -> ControlFlow<()> {
+        clear_background(WHITE);
+
+        if let Some((player, action)) = self.rebinding {
+            if is_key_pressed(KeyCode::Escape) {
+                self.rebinding = None;
+            } else if let Some(&(_, key)) = KEYS.iter().find(|(_, key)| is_key_pressed(*key)) {
+                self.config.bindings[player].set_key(action, key);
+                self.config.save();
+                self.rebinding = None;
+            }
+        } else if is_key_pressed(KeyCode::Escape) {
+            return ControlFlow::Break(());
+        }
+
+        let mut close = false;
+        egui::CentralPanel::default().show(&comfy::egui(), |ui| {
+            ui.with_layout(
+                egui::Layout::top_down_justified(egui::Align::Center),
+                |ui| {
+                    ui.add(egui::Label::new("Controls"));
+
+                    egui::Grid::new("bindings").show(ui, |ui| {
+                        ui.label("");
+                        ui.label("Player 1");
+                        ui.label("Player 2");
+                        ui.end_row();
+
+                        for action in Action::ALL {
+                            ui.label(action.name());
+                            for player in 0..self.config.bindings.len() {
+                                let text = if self.rebinding == Some((player, action)) {
+                                    "Press a key..."
+                                } else {
+                                    key_name(self.config.bindings[player].key(action))
+                                };
+                                // Only mouse clicks, since the key being bound
+                                // could otherwise press the button again.
+                                if ui.button(text).clicked_by(egui::PointerButton::Primary) {
+                                    self.rebinding = Some((player, action));
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                    let socd = self.config.socd;
+                    egui::ComboBox::from_label("Left + Right")
+                        .selected_text(socd.name())
+                        .show_ui(ui, |ui| {
+                            for mode in SocdMode::ALL {
+                                ui.selectable_value(&mut self.config.socd, mode, mode.name());
+                            }
+                        });
+                    if self.config.socd != socd {
+                        self.config.save();
+                    }
+
+                    if ui.button("Reset to Defaults").clicked() {
+                        self.config = Config::default();
+                        self.config.save();
+                        self.rebinding = None;
+                    }
+                    if ui.button("Back to Menu").clicked() {
+                        close = true;
+                    }
+                },
+            );
+        });
+
+        if close {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! Turning local devices into `Input`s.
+
+use std::{cell::RefCell, collections::BTreeMap};
+
+use comfy::*;
+use fungus_sim::Input;
+use gilrs::{Axis, Button, GamepadId, Gilrs};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    Left,
+    Right,
+    Jump,
+    Down,
+    Light,
+    Medium,
+    Heavy,
+    Throw,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::Left,
+        Action::Right,
+        Action::Jump,
+        Action::Down,
+        Action::Light,
+        Action::Medium,
+        Action::Heavy,
+        Action::Throw,
+    ];
+
+    fn bit(self) -> u8 {
+        match self {
+            Action::Left => Input::LEFT,
+            Action::Right => Input::RIGHT,
+            Action::Jump => Input::JUMP,
+            Action::Down => Input::DOWN,
+            Action::Light => Input::LIGHT,
+            Action::Medium => Input::MEDIUM,
+            Action::Heavy => Input::HEAVY,
+            Action::Throw => Input::THROW,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Jump => "Jump",
+            Action::Down => "Down",
+            Action::Light => "Light",
+            Action::Medium => "Medium",
+            Action::Heavy => "Heavy",
+            Action::Throw => "Throw",
+        }
+    }
+}
+
+macro_rules! keys {
+    ($($key:ident),* $(,)?) => {
+        &[$((stringify!($key), KeyCode::$key)),*]
+    };
+}
+
+/// Keys that can be bound, and their names in the config file.
+pub const KEYS: &[(&str, KeyCode)] = keys![
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Key0, Key1, Key2,
+    Key3, Key4, Key5, Key6, Key7, Key8, Key9, Left, Right, Up, Down, Space, Return, Tab, LShift,
+    RShift, LControl, RControl, LAlt, RAlt, Comma, Period, Slash, Semicolon,
+];
+
+pub fn key_name(key: KeyCode) -> &'static str {
+    KEYS.iter()
+        .find(|(_, k)| *k == key)
+        .map_or("?", |(name, _)| name)
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEYS.iter().find(|(n, _)| *n == name).map(|(_, key)| *key)
+}
+
+/// The keys of one local player.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    into = "BTreeMap<Action, String>",
+    try_from = "BTreeMap<Action, String>"
+)]
+pub struct Bindings {
+    /// Indexed by `Action as usize`.
+    keys: [KeyCode; Action::ALL.len()],
+}
+
+impl Bindings {
+    /// Player 1 is on WASD with space, E and R to attack and F to throw,
+    /// player 2 on the arrow keys with enter, slash and period to attack and
+    /// right shift to throw.
+    pub fn default_for(local_player: usize) -> Self {
+        let keys = match local_player {
+            0 => [
+                KeyCode::A,
+                KeyCode::D,
+                KeyCode::W,
+                KeyCode::S,
+                KeyCode::Space,
+                KeyCode::E,
+                KeyCode::R,
+                KeyCode::F,
+            ],
+            _ => [
+                KeyCode::Left,
+                KeyCode::Right,
+                KeyCode::Up,
+                KeyCode::Down,
+                KeyCode::Return,
+                KeyCode::Slash,
+                KeyCode::Period,
+                KeyCode::RShift,
+            ],
+        };
+        Bindings { keys }
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys[action as usize]
+    }
+
+    pub fn set_key(&mut self, action: Action, key: KeyCode) {
+        self.keys[action as usize] = key;
+    }
+
+    fn keyboard_input(&self) -> Input {
+        let mut bits = 0u8;
+        for action in Action::ALL {
+            if is_key_down(self.key(action)) {
+                bits |= action.bit();
+            }
+        }
+        Input { input_bits: bits }
+    }
+}
+
+impl From<Bindings> for BTreeMap<Action, String> {
+    fn from(bindings: Bindings) -> Self {
+        Action::ALL
+            .into_iter()
+            .map(|action| (action, key_name(bindings.key(action)).to_string()))
+            .collect()
+    }
+}
+
+impl TryFrom<BTreeMap<Action, String>> for Bindings {
+    type Error = String;
+
+    fn try_from(names: BTreeMap<Action, String>) -> Result<Self, String> {
+        let mut keys = [KeyCode::Space; Action::ALL.len()];
+        for action in Action::ALL {
+            let name = names
+                .get(&action)
+                .ok_or_else(|| format!("no key bound to {}", action.name()))?;
+            keys[action as usize] =
+                key_from_name(name).ok_or_else(|| format!("unknown key {name}"))?;
+        }
+        Ok(Bindings { keys })
+    }
+}
+
+/// How to resolve left and right being held at once (simultaneous opposite
+/// cardinal directions).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SocdMode {
+    /// Neither direction.
+    #[default]
+    Neutral,
+    /// Whichever was pressed most recently.
+    LastInputWins,
+    /// Whichever was held first.
+    FirstInputWins,
+}
+
+impl SocdMode {
+    pub const ALL: [SocdMode; 3] = [
+        SocdMode::Neutral,
+        SocdMode::LastInputWins,
+        SocdMode::FirstInputWins,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SocdMode::Neutral => "Neutral",
+            SocdMode::LastInputWins => "Last input wins",
+            SocdMode::FirstInputWins => "First input wins",
+        }
+    }
+}
+
+/// Applies a `SocdMode` to one local player's input, before it's sent
+/// anywhere, so peers and replays only ever see the resolved direction.
+#[derive(Debug, Clone)]
+pub struct SocdCleaner {
+    mode: SocdMode,
+    /// Left and right bits last frame, before and after cleaning.
+    previous_raw: u8,
+    previous_clean: u8,
+}
+
+impl SocdCleaner {
+    const HORIZONTAL: u8 = Input::LEFT | Input::RIGHT;
+
+    pub fn new(mode: SocdMode) -> Self {
+        SocdCleaner {
+            mode,
+            previous_raw: 0,
+            previous_clean: 0,
+        }
+    }
+
+    pub fn clean(&mut self, input: Input) -> Input {
+        let raw = input.input_bits & Self::HORIZONTAL;
+        let clean = if raw != Self::HORIZONTAL {
+            raw
+        } else if self.previous_raw == Self::HORIZONTAL {
+            // Still both held, keep whatever we decided when that started.
+            self.previous_clean
+        } else {
+            // If both went down on the same frame there's nothing to go on,
+            // so that's neutral in every mode.
+            let first = self.previous_raw;
+            match self.mode {
+                SocdMode::Neutral => 0,
+                SocdMode::LastInputWins if first != 0 => Self::HORIZONTAL & !first,
+                SocdMode::FirstInputWins => first,
+                SocdMode::LastInputWins => 0,
+            }
+        };
+
+        self.previous_raw = raw;
+        self.previous_clean = clean;
+        Input {
+            input_bits: (input.input_bits & !Self::HORIZONTAL) | clean,
+        }
+    }
+}
+
+/// Where a local player's input comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    Keyboard,
+    Gamepad(GamepadId),
+}
+
+/// How far a stick has to be pushed to count as a direction.
+const STICK_DEADZONE: f32 = 0.5;
+
+const PAD_BUTTONS: &[(Button, u8)] = &[
+    (Button::DPadLeft, Input::LEFT),
+    (Button::DPadRight, Input::RIGHT),
+    (Button::DPadUp, Input::JUMP),
+    (Button::DPadDown, Input::DOWN),
+    (Button::South, Input::JUMP),
+    (Button::West, Input::LIGHT),
+    (Button::North, Input::MEDIUM),
+    (Button::RightTrigger, Input::HEAVY),
+    (Button::East, Input::THROW),
+];
+
+struct Gamepads {
+    /// `None` if the platform has no gamepad support.
+    gilrs: Option<Gilrs>,
+    /// Indexed by local player, in handle order.
+    devices: [Device; 2],
+}
+
+thread_local! {
+    static GAMEPADS: RefCell<Gamepads> = RefCell::new(Gamepads {
+        gilrs: Gilrs::new()
+            .map_err(|e| println!("Gamepads unavailable: {e}"))
+            .ok(),
+        devices: [Device::Keyboard; 2],
+    });
+}
+
+/// Catches up on gamepad events. Call once per frame, before reading input.
+pub fn update_gamepads() {
+    GAMEPADS.with_borrow_mut(|pads| {
+        let Some(gilrs) = &mut pads.gilrs else {
+            return;
+        };
+        while let Some(event) = gilrs.next_event() {
+            if let gilrs::EventType::Disconnected = event.event {
+                for device in &mut pads.devices {
+                    if *device == Device::Gamepad(event.id) {
+                        *device = Device::Keyboard;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Connected gamepads, with a name to show for each.
+pub fn connected_gamepads() -> Vec<(GamepadId, String)> {
+    GAMEPADS.with_borrow(|pads| {
+        let Some(gilrs) = &pads.gilrs else {
+            return Vec::new();
+        };
+        gilrs
+            .gamepads()
+            .map(|(id, pad)| (id, format!("{} ({id})", pad.name())))
+            .collect()
+    })
+}
+
+pub fn device(local_player: usize) -> Device {
+    GAMEPADS.with_borrow(|pads| pads.devices[local_player])
+}
+
+pub fn set_device(local_player: usize, device: Device) {
+    GAMEPADS.with_borrow_mut(|pads| pads.devices[local_player] = device);
+}
+
+/// The input of the `local_player`th local player, from whichever device
+/// they were assigned in the menu.
+pub fn local_input(local_player: usize, bindings: &Bindings) -> Input {
+    match device(local_player) {
+        Device::Keyboard => bindings.keyboard_input(),
+        Device::Gamepad(id) => gamepad_input(id),
+    }
+}
+
+fn gamepad_input(id: GamepadId) -> Input {
+    GAMEPADS.with_borrow(|pads| {
+        let Some(pad) = pads
+            .gilrs
+            .as_ref()
+            .and_then(|gilrs| gilrs.connected_gamepad(id))
+        else {
+            return Input::default();
+        };
+
+        let mut bits = 0u8;
+        for &(button, bit) in PAD_BUTTONS {
+            if pad.is_pressed(button) {
+                bits |= bit;
+            }
+        }
+
+        let x = pad.value(Axis::LeftStickX);
+        let y = pad.value(Axis::LeftStickY);
+        if x <= -STICK_DEADZONE {
+            bits |= Input::LEFT;
+        }
+        if x >= STICK_DEADZONE {
+            bits |= Input::RIGHT;
+        }
+        if y >= STICK_DEADZONE {
+            bits |= Input::JUMP;
+        }
+        if y <= -STICK_DEADZONE {
+            bits |= Input::DOWN;
+        }
+
+        Input { input_bits: bits }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: u8 = Input::LEFT;
+    const RIGHT: u8 = Input::RIGHT;
+    const BOTH: u8 = Input::LEFT | Input::RIGHT;
+
+    /// Cleans each frame of `raw` in turn, returning what's sent.
+    fn clean(mode: SocdMode, raw: &[u8]) -> Vec<u8> {
+        let mut cleaner = SocdCleaner::new(mode);
+        raw.iter()
+            .map(|&input_bits| cleaner.clean(Input { input_bits }).input_bits)
+            .collect()
+    }
+
+    #[test]
+    fn neutral_cancels_out() {
+        let raw = [LEFT, BOTH, BOTH, RIGHT];
+        assert_eq!(clean(SocdMode::Neutral, &raw), [LEFT, 0, 0, RIGHT]);
+    }
+
+    #[test]
+    fn last_input_wins_switches_to_the_new_direction() {
+        let raw = [LEFT, BOTH, BOTH, LEFT, RIGHT, BOTH];
+        assert_eq!(
+            clean(SocdMode::LastInputWins, &raw),
+            [LEFT, RIGHT, RIGHT, LEFT, RIGHT, LEFT]
+        );
+    }
+
+    #[test]
+    fn first_input_wins_keeps_the_held_direction() {
+        let raw = [LEFT, BOTH, BOTH, RIGHT, BOTH];
+        assert_eq!(
+            clean(SocdMode::FirstInputWins, &raw),
+            [LEFT, LEFT, LEFT, RIGHT, RIGHT]
+        );
+    }
+
+    #[test]
+    fn pressing_both_at_once_is_neutral_in_every_mode() {
+        for mode in SocdMode::ALL {
+            assert_eq!(clean(mode, &[0, BOTH, BOTH]), [0, 0, 0], "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn other_buttons_are_left_alone() {
+        for mode in SocdMode::ALL {
+            let raw = [LEFT | Input::JUMP, BOTH | Input::LIGHT];
+            let cleaned = clean(mode, &raw);
+            assert_eq!(cleaned[0], LEFT | Input::JUMP, "{mode:?}");
+            assert_eq!(cleaned[1] & !BOTH, Input::LIGHT, "{mode:?}");
+        }
+    }
+}
@@ -1,56 +1,40 @@
-mod animation;
+mod config;
+mod controls;
+mod input;
+mod render;
+mod replay;
 
-use std::{mem, ops::ControlFlow};
+use std::{collections::BTreeMap, mem, ops::ControlFlow};
 
 use ::include_dir::{Dir, DirEntry};
-use animation::{Animation, AnimationData};
-use bytemuck::Pod;
-use comfy::{bytemuck::Zeroable, *};
-use ggrs::{GgrsError, NonBlockingSocket, P2PSession, SessionBuilder, SessionState};
+use comfy::*;
+use config::Config;
+use controls::ControlsMenu;
+use fungus_sim::{GameData, GameState, Input, MatchSettings, Phase, Replay, StockMode};
+use ggrs::{
+    DesyncDetection, Frame, GgrsError, GgrsEvent, NonBlockingSocket, P2PSession, SessionBuilder,
+    SessionState,
+};
+use input::{Bindings, Device, SocdCleaner};
 use matchbox_socket::{PeerId, WebRtcSocket};
+use replay::ReplayViewer;
 
 simple_game!("Goose Fighter", App, setup, update);
 
-/// The screen is always [-1, 1]
-///
-/// This means 30 pixels in 0.2 (1 fifth) of a half window.
-// const SPRITE_PIXELS_PER_WINDOW_POINT: f32 = 16. / 0.2;
-
-const PLAYER_SPEED: f32 = 0.01;
-
-#[derive(Clone, PartialEq, Eq)]
-enum PlayerState {
-    Idle,
-    Recoiling,
-    Blocking,
-    Attacking,
-    Death,
-}
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Direction {
-    East,
-    West,
-}
-
-#[derive(Clone)]
-struct Player {
-    facing: Direction,
-    loc: Vec2,
-    /// How many more times can you safely block an attack.
-    shield: u32,
-    velocity: Vec2,
-    tint: Color,
-
-    // Animation counts frames, and is authoratative
-    animation: Animation,
-    state: PlayerState,
-}
-
 enum App {
-    StartMenu { server: String },
-    Connecting { socket: Option<WebRtcSocket> },
+    StartMenu {
+        server: String,
+        settings: MatchSettings,
+        replay_path: String,
+        error: Option<String>,
+    },
+    Connecting {
+        socket: Option<WebRtcSocket>,
+        settings: MatchSettings,
+    },
     InGame(Game),
+    WatchingReplay(ReplayViewer),
+    Controls(ControlsMenu),
 }
 
 struct Game {
@@ -61,21 +45,21 @@ struct Game {
     accumulator: Duration,
 
     state: GameState,
-
-    animations: Animations,
-}
-
-type Animations = HashMap<String, Rc<AnimationData>>;
-
-#[derive(Clone)]
-enum GameState {
-    Playing(PlayingState),
-    ScoreScreen { winner: usize },
-}
-
-#[derive(Clone)]
-struct PlayingState {
-    players: [Player; 2],
+    /// Recent states by frame, to dump if a desync is detected.
+    saved_states: BTreeMap<Frame, GameState>,
+    /// First frame a desync was detected on.
+    desync: Option<Frame>,
+    opponent_left: bool,
+
+    /// The next frame to simulate.
+    frame: usize,
+    replay: Replay,
+    replay_saved: bool,
+
+    /// Keys for each local player, in handle order.
+    bindings: [Bindings; 2],
+    socd: [SocdCleaner; 2],
+    data: GameData,
 }
 
 /// `GGRSConfig` holds all type parameters for GGRS Sessions
@@ -87,30 +71,18 @@ impl ggrs::Config for GGRSConfig {
     type Address = PeerId;
 }
 
-#[repr(C)]
-#[derive(PartialEq, Eq, Copy, Clone, Pod, Zeroable)]
-pub struct Input {
-    /// Bit 0: left
-    ///
-    /// Bit 1: right
-    ///
-    /// Bit 2: attack
-    // TODO: Bitfield crate? Needs to be ": Pod"
-    input_bits: u8,
-}
-
-impl Input {
-    const LEFT: u8 = 0b001;
-    const RIGHT: u8 = 0b010;
-    const ATTACK: u8 = 0b100;
-    const JUMP: u8 = 0b1000;
-}
-
 impl App {
     fn new(_e: &mut EngineState) -> Self {
+        Self::new_menu()
+    }
+
+    fn new_menu() -> Self {
         App::StartMenu {
             // server: "localhost:3536".into(),
             server: "gregs-macbook-air:3536".into(),
+            settings: MatchSettings::default(),
+            replay_path: replay::DEFAULT_REPLAY_PATH.into(),
+            error: None,
         }
     }
 }
@@ -166,8 +138,15 @@ impl NonBlockingSocket<PeerId> for FakeSocket {
 }
 
 fn update(app: &mut App, _c: &mut EngineContext) {
+    input::update_gamepads();
+
     match app {
-        App::StartMenu { ref mut server } => {
+        App::StartMenu {
+            ref mut server,
+            ref mut settings,
+            ref mut replay_path,
+            ref mut error,
+        } => {
             clear_background(WHITE);
 
             let new_app = comfy::egui::CentralPanel::default().show(&comfy::egui(), |ui| {
@@ -175,11 +154,34 @@ fn update(app: &mut App, _c: &mut EngineContext) {
                     egui::Layout::top_down_justified(egui::Align::Center),
                     |ui| {
                         ui.add(egui::Label::new("Goose Fighter"));
+                        ui.horizontal(|ui| {
+                            let mode = &mut settings.stock_mode;
+                            ui.radio_value(mode, StockMode::PerRound, "Stocks per round");
+                            ui.radio_value(mode, StockMode::PerMatch, "Stocks per match");
+                        });
+                        // A match on stocks is a single round.
+                        if settings.stock_mode == StockMode::PerRound {
+                            ui.add(
+                                egui::Slider::new(&mut settings.first_to, 1..=5).text("First to"),
+                            );
+                        }
+                        ui.add(egui::Slider::new(&mut settings.stocks, 1..=5).text("Stocks"));
+                        ui.add(
+                            egui::Slider::new(&mut settings.buffer_frames, 0..=10)
+                                .text("Input buffer (frames)"),
+                        );
                         if ui.button("Start Local").clicked() {
+                            if let Err(e) = settings.validate() {
+                                *error = Some(e);
+                                return None;
+                            }
                             let mut session = SessionBuilder::<GGRSConfig>::new()
                                 .with_num_players(2)
                                 .with_fps(60)
-                                .unwrap();
+                                .unwrap()
+                                .with_desync_detection_mode(DesyncDetection::On {
+                                    interval: DESYNC_INTERVAL,
+                                });
 
                             for i in 0..2 {
                                 session = session.add_player(ggrs::PlayerType::Local, i).unwrap();
@@ -187,34 +189,72 @@ fn update(app: &mut App, _c: &mut EngineContext) {
 
                             let session = session.start_p2p_session(FakeSocket::default()).unwrap();
 
-                            return Some(start_game(session));
+                            return Some(start_game(session, *settings));
                         }
 
                         if ui.button("Start Remote").clicked() {
+                            // Checked before they go into the room name.
+                            if let Err(e) = settings.validate() {
+                                *error = Some(e);
+                                return None;
+                            }
                             info!("Constructing socket...");
                             // TODO: Use builder, more channels.
                             // let (socket, message_loop) = WebRtcSocket::new_ggrs("ws://206.172.98.17:3536/?next=2");
                             // let (socket, message_loop) = WebRtcSocket::new_ggrs("ws://206.172.98.17:80/foo");
                             // TODO: Sort of a injection vulnerability.
-                            let (socket, message_loop) =
-                                WebRtcSocket::new_ggrs(format!("ws://{server}/foo"));
-
+                            // Only players who picked the same settings get
+                            // matched up, since they share a room.
+                            let (socket, message_loop) = WebRtcSocket::new_ggrs(format!(
+                                "ws://{server}/foo_first_to_{}_stocks_{}_{:?}_buffer_{}",
+                                settings.first_to,
+                                settings.stocks,
+                                settings.stock_mode,
+                                settings.buffer_frames
+                            ));
+
+                            // The loop exits once the socket is dropped, when
+                            // the game goes back to the menu.
                             #[cfg(not(target_arch = "wasm32"))]
                             std::thread::spawn(move || {
-                                futures_lite::future::block_on(message_loop).unwrap();
-                                panic!("Network socket message loop exited");
+                                let result = futures_lite::future::block_on(message_loop);
+                                info!("Network socket message loop exited: {result:?}");
                             });
                             #[cfg(target_arch = "wasm32")]
                             wasm_bindgen_futures::spawn_local(async move {
-                                message_loop.await.unwrap();
-                                panic!("Network socket message loop exited");
+                                let result = message_loop.await;
+                                info!("Network socket message loop exited: {result:?}");
                             });
 
                             return Some(App::Connecting {
                                 socket: Some(socket),
+                                settings: *settings,
                             });
                         }
                         ui.text_edit_singleline(server);
+
+                        if ui.button("Watch Replay").clicked() {
+                            let data = load_game_data();
+                            match replay::load(replay_path, &data) {
+                                Ok(replay) => {
+                                    return Some(App::WatchingReplay(ReplayViewer::new(
+                                        replay, data,
+                                    )));
+                                }
+                                Err(e) => *error = Some(e),
+                            }
+                        }
+                        ui.text_edit_singleline(replay_path);
+
+                        select_devices(ui);
+
+                        if ui.button("Controls").clicked() {
+                            return Some(App::Controls(ControlsMenu::new()));
+                        }
+
+                        if let Some(error) = error {
+                            ui.colored_label(egui::Color32::RED, error.as_str());
+                        }
                         None
                     },
                 )
@@ -231,7 +271,7 @@ fn update(app: &mut App, _c: &mut EngineContext) {
             //  - Start Local
             //  - Connect to server [IP]
         }
-        App::Connecting { socket } => {
+        App::Connecting { socket, settings } => {
             let socket_ref = socket.as_mut().unwrap();
             socket_ref.update_peers();
             let connected_count = socket_ref.connected_peers().count();
@@ -243,7 +283,10 @@ fn update(app: &mut App, _c: &mut EngineContext) {
                 let mut session = SessionBuilder::<GGRSConfig>::new()
                     .with_num_players(2)
                     .with_fps(60)
-                    .unwrap();
+                    .unwrap()
+                    .with_desync_detection_mode(DesyncDetection::On {
+                        interval: DESYNC_INTERVAL,
+                    });
 
                 let mut socket: WebRtcSocket = socket.take().unwrap();
 
@@ -253,17 +296,66 @@ fn update(app: &mut App, _c: &mut EngineContext) {
 
                 let session = session.start_p2p_session(socket).unwrap();
 
-                *app = start_game(session);
+                *app = start_game(session, *settings);
             }
         }
-        App::InGame(game) => game.update(),
+        App::InGame(game) => {
+            if game.update().is_break() {
+                game.close();
+                *app = App::new_menu();
+            }
+        }
+        App::WatchingReplay(viewer) => {
+            if viewer.update().is_break() {
+                *app = App::new_menu();
+            }
+        }
+        App::Controls(menu) => {
+            if menu.update().is_break() {
+                *app = App::new_menu();
+            }
+        }
+    }
+}
+
+/// Lets each local player pick the keyboard or a gamepad. Online, only
+/// player 1's choice is used.
+fn select_devices(ui: &mut egui::Ui) {
+    let pads = input::connected_gamepads();
+    for local_player in 0..2 {
+        let mut device = input::device(local_player);
+        let name = |device: Device| match device {
+            Device::Keyboard => "Keyboard".to_string(),
+            Device::Gamepad(id) => pads
+                .iter()
+                .find(|(pad, _)| *pad == id)
+                .map_or("Disconnected gamepad".to_string(), |(_, name)| name.clone()),
+        };
+
+        egui::ComboBox::from_label(format!("Player {} controller", local_player + 1))
+            .selected_text(name(device))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut device, Device::Keyboard, name(Device::Keyboard));
+                for (id, pad_name) in &pads {
+                    ui.selectable_value(&mut device, Device::Gamepad(*id), pad_name.as_str());
+                }
+            });
+        input::set_device(local_player, device);
     }
 }
 
-const MAX_SHIELD: u32 = 3;
+/// Frames between checksum comparisons with the remote peer.
+const DESYNC_INTERVAL: u32 = 10;
+
+/// How many frames of history to keep for desync dumps. Checksums are only
+/// compared once a frame is confirmed, so this needs to cover the round trip.
+const KEPT_STATES: Frame = 300;
 
-fn start_game(session: P2PSession<GGRSConfig>) -> App {
-    let animations = animation::load_animations();
+/// `settings` have to be valid.
+fn start_game(session: P2PSession<GGRSConfig>, settings: MatchSettings) -> App {
+    let data = load_game_data();
+    let state = GameState::new(settings, &data).expect("settings are checked in the menu");
+    let config = Config::load();
 
     App::InGame(Game {
         session,
@@ -271,44 +363,53 @@ fn start_game(session: P2PSession<GGRSConfig>) -> App {
         last_update: Instant::now(),
         accumulator: Duration::ZERO,
 
-        state: GameState::Playing(PlayingState {
-            players: [
-                Player {
-                    tint: Color::rgb(1.0, 0.8, 0.8),
-                    facing: Direction::East,
-                    animation: animations["standing"].to_anim(),
-                    loc: Vec2::new(-0.5, 0.0),
-                    velocity: Vec2::ZERO,
-                    state: PlayerState::Idle,
-                    shield: MAX_SHIELD,
-                },
-                Player {
-                    tint: Color::rgb(0.8, 0.8, 1.0),
-                    facing: Direction::West,
-                    animation: animations["standing"].to_anim(),
-                    loc: Vec2::new(0.5, 0.0),
-                    velocity: Vec2::ZERO,
-                    state: PlayerState::Idle,
-                    shield: MAX_SHIELD,
-                },
-            ],
-        }),
-
-        animations,
+        saved_states: BTreeMap::new(),
+        desync: None,
+        opponent_left: false,
+
+        frame: 0,
+        replay: Replay::new(&data, &state),
+        replay_saved: false,
+
+        bindings: config.bindings,
+        socd: [SocdCleaner::new(config.socd), SocdCleaner::new(config.socd)],
+        state,
+        data,
     })
 }
 
+fn load_game_data() -> GameData {
+    let dir = assets_dir();
+    fungus_sim::load_game_data(dir.files().map(|file| (file.path(), file.contents())))
+}
+
 const FPS: f64 = 60.0;
 
 impl Game {
-    fn update(&mut self) {
+    /// Breaks when the player wants to go back to the menu.
+    fn update(&mut self) -> ControlFlow<()> {
+        if is_key_pressed(KeyCode::Escape) {
+            return ControlFlow::Break(());
+        }
+
         // communicate, receive and send packets
         // TODO: Do we need this? It does it implicitly in advance_frame.
         self.session.poll_remote_clients();
 
         // print GGRS events
-        for event in self.session.events() {
+        let events: Vec<_> = self.session.events().collect();
+        for event in events {
             println!("Event: {:?}", event);
+            match event {
+                GgrsEvent::DesyncDetected {
+                    frame,
+                    local_checksum,
+                    remote_checksum,
+                    ..
+                } => self.report_desync(frame, local_checksum, remote_checksum),
+                GgrsEvent::Disconnected { .. } => self.opponent_left = true,
+                _ => (),
+            }
         }
 
         // this is to keep ticks between clients synchronized.
@@ -333,9 +434,9 @@ impl Game {
                 let mut handles = self.session.local_player_handles();
                 handles.sort();
                 for (idx, player) in handles.into_iter().enumerate() {
-                    self.session
-                        .add_local_input(player, get_local_input(idx))
-                        .unwrap();
+                    let input = input::local_input(idx, &self.bindings[idx]);
+                    let input = self.socd[idx].clean(input);
+                    self.session.add_local_input(player, input).unwrap();
                 }
                 match self.session.advance_frame() {
                     Ok(requests) => self.handle_requests(requests),
@@ -347,379 +448,131 @@ impl Game {
             }
         }
 
+        self.save_replay_once_confirmed();
+
         self.render();
-    }
 
-    fn handle_requests(&mut self, requests: Vec<ggrs::GgrsRequest<GGRSConfig>>) {
-        for req in requests {
-            match req {
-                ggrs::GgrsRequest::SaveGameState { cell, frame } => {
-                    cell.save(frame, Some(self.state.clone()), None);
-                }
-                ggrs::GgrsRequest::LoadGameState { cell, frame: _ } => {
-                    self.state = cell.load().unwrap();
-                }
-                ggrs::GgrsRequest::AdvanceFrame { inputs } => {
-                    self.advance_frame(inputs);
-                }
-            }
-        }
+        ControlFlow::Continue(())
     }
 
-    fn render(&self) {
-        match &self.state {
-            GameState::Playing(playing_state) => playing_state.render(),
-            GameState::ScoreScreen { winner } => {
-                clear_background(WHITE);
-                let handles = self.session.local_player_handles();
-                let msg =
-                    if handles.len() == 1 {
-                        if *winner == handles[0] { "You won!" } else { "They won :(" }
-                    } else {
-                        &format!("Player {} won!", *winner + 1)
-                    };
-                draw_text(msg, Vec2::ZERO, BLACK, TextAlign::Center);
+    /// Lets the remote player know we're leaving. Dropping the session
+    /// afterwards closes the socket.
+    fn close(&mut self) {
+        for handle in self.session.remote_player_handles() {
+            if let Err(e) = self.session.disconnect_player(handle) {
+                println!("Failed to disconnect player {handle}: {e}");
             }
         }
     }
 
-    fn advance_frame(&mut self, inputs: Vec<(Input, ggrs::InputStatus)>) {
-        match &mut self.state {
-            GameState::Playing(playing_state) => {
-                let state_transition = playing_state.update(inputs, &self.animations);
-                if let Some(new_state) = state_transition {
-                    self.state = new_state;
-                }
-            }
-            // TODO: start the next round at some point
-            GameState::ScoreScreen { .. } => (),
+    /// Saves the replay once the match is over, and every frame simulated so
+    /// far is confirmed (so no rollback can change the recorded inputs).
+    fn save_replay_once_confirmed(&mut self) {
+        if !matches!(self.state.phase, Phase::MatchOver { .. }) {
+            // Rematches keep recording into the same replay, so save it again
+            // at the end of the next match.
+            self.replay_saved = false;
+            return;
         }
-    }
-}
-
-fn get_local_input(idx: usize) -> Input {
-    let mut bits = 0u8;
-
-    match idx {
-        0 => {
-            if is_key_down(KeyCode::A) {
-                bits |= Input::LEFT;
-            }
-            if is_key_down(KeyCode::D) {
-                bits |= Input::RIGHT;
-            }
-            if is_key_down(KeyCode::Space) {
-                bits |= Input::ATTACK;
-            }
-            if is_key_down(KeyCode::W) {
-                bits |= Input::JUMP;
-            }
+        if self.replay_saved {
+            return;
         }
-        1 => {
-            if is_key_down(KeyCode::Left) {
-                bits |= Input::LEFT;
-            }
-            if is_key_down(KeyCode::Right) {
-                bits |= Input::RIGHT;
-            }
-            if is_key_down(KeyCode::Down) {
-                bits |= Input::ATTACK;
-            }
-            if is_key_down(KeyCode::Up) {
-                bits |= Input::JUMP;
-            }
+        let confirmed = self.session.confirmed_frame();
+        if confirmed < 0 || (confirmed as usize) + 1 < self.frame {
+            return;
         }
-        _ => unimplemented!(),
-    }
-
-    Input { input_bits: bits }
-}
 
-impl Input {
-    fn is_attack_pressed(self) -> bool {
-        self.input_bits & Self::ATTACK != 0
+        self.replay_saved = true;
+        replay::save(&self.replay, replay::DEFAULT_REPLAY_PATH);
     }
 
-    fn is_left_pressed(self) -> bool {
-        self.input_bits & Self::LEFT != 0
-    }
-
-    fn is_right_pressed(self) -> bool {
-        self.input_bits & Self::RIGHT != 0
-    }
-
-    fn is_jump_pressed(self) -> bool {
-        self.input_bits & Self::JUMP != 0
-    }
-}
+    fn handle_requests(&mut self, requests: Vec<ggrs::GgrsRequest<GGRSConfig>>) {
+        for req in requests {
+            match req {
+                ggrs::GgrsRequest::SaveGameState { cell, frame } => {
+                    let checksum = self.state.checksum();
+                    cell.save(frame, Some(self.state.clone()), Some(checksum));
 
-impl PlayingState {
-    fn update(
-        &mut self,
-        inputs: Vec<(Input, ggrs::InputStatus)>,
-        anims: &Animations,
-    ) -> Option<GameState> {
-        // Transition states
-
-        for (i, p) in self.players.iter_mut().enumerate() {
-            if matches!(p.animation.next_frame(), ControlFlow::Break(())) {
-                if p.state == PlayerState::Death {
-                    return Some(GameState::ScoreScreen { winner: 1 - i });
+                    self.saved_states.insert(frame, self.state.clone());
+                    self.saved_states.retain(|&f, _| f > frame - KEPT_STATES);
                 }
-                p.start_idle();
-            }
-        }
-
-        if self.players[0].loc.x < self.players[1].loc.x {
-            self.players[0].facing = Direction::East;
-            self.players[1].facing = Direction::West;
-        } else if self.players[0].loc.x > self.players[1].loc.x {
-            self.players[0].facing = Direction::West;
-            self.players[1].facing = Direction::East;
-        }
-        // Else, equal, change nothing.
-
-        // HANDLE INPUT
-
-        // NOTE: start_idle may not be called after this point.
-        // we rely on input handling to put us in the right walking animation.
-
-        for i in 0..2 {
-            let mut x_accel: f32 = 0.0;
-            if matches!(self.players[i].state, PlayerState::Idle { .. }) {
-                if inputs[i].0.is_attack_pressed() {
-                    self.players[i].start_attack(anims);
-                } else {
-                    let left = inputs[i].0.is_left_pressed();
-                    let right = inputs[i].0.is_right_pressed();
-                    let jump = inputs[i].0.is_jump_pressed();
-
-                    let forwards = (left && (self.players[i].facing == Direction::West))
-                        || (right && (self.players[i].facing == Direction::East));
-                    let backwards = (left && (self.players[i].facing == Direction::East))
-                        || (right && (self.players[i].facing == Direction::West));
-
-                    match (forwards, backwards, jump) {
-                        (_, _, true) if self.players[i].loc.y == 0.0 => {
-                            // TODO: Jump?
-                            self.players[i].velocity.y = 0.05;
-                            self.players[i].ensure_standing(anims);
-                        }
-                        (true, false, _) => {
-                            x_accel = PLAYER_SPEED;
-                            self.players[i].ensure_walking_forwards(anims);
-                        }
-                        (false, true, _) => {
-                            x_accel = -PLAYER_SPEED;
-                            self.players[i].ensure_walking_backwards(anims);
-                        }
-                        (true, true, _) | (false, false, _) => {
-                            self.players[i].ensure_standing(anims);
-                        }
-                    }
+                ggrs::GgrsRequest::LoadGameState { cell, frame } => {
+                    self.state = cell.load().unwrap();
+                    self.frame = frame as usize;
                 }
-            }
-            self.players[i].accelerate(x_accel);
-        }
-
-        for p in &mut self.players {
-            p.update_loc();
-        }
-
-        // Handle attacks
-        let hurtboxes = self.hurtboxes();
-        let hitboxes = self.hitboxes();
-
-        let hits = [
-            hitboxes[0]
-                .zip(hurtboxes[1])
-                .is_some_and(|(hit, hurt)| hit.intersects(&hurt)),
-            hitboxes[1]
-                .zip(hurtboxes[0])
-                .is_some_and(|(hit, hurt)| hit.intersects(&hurt)),
-        ];
-
-        match hits {
-            [true, true] => {
-                for p in self.players.iter_mut() {
-                    p.start_recoil(anims);
+                ggrs::GgrsRequest::AdvanceFrame { inputs } => {
+                    self.advance_frame(inputs);
                 }
             }
-            [true, false] => self.players[1].handle_hit(anims),
-            [false, true] => self.players[0].handle_hit(anims),
-            [false, false] => (),
         }
-
-        None
-    }
-
-    fn hitboxes(&self) -> [Option<AABB>; 2] {
-        self.players.each_ref().map(|p| p.hitbox())
-    }
-
-    fn hurtboxes(&self) -> [Option<AABB>; 2] {
-        self.players.each_ref().map(|p| p.hurtbox())
     }
 
     fn render(&self) {
-        clear_background(WHITE);
-
-        for b in self.hurtboxes() {
-            if let Some(b) = b {
-                draw_rect_outline(b.center(), b.size(), 0.01, DARKGREEN, 1);
-            }
-        }
-
-        for b in self.hitboxes() {
-            if let Some(b) = b {
-                // TODO: Not pixel perfect, border extends past hitbox.
-                draw_rect_outline(b.center(), b.size(), 0.01, DARKRED, 2);
-            }
-        }
-
-        for p in &self.players {
-            p.render_sprite();
-        }
-
-        draw_rect(
-            Vec2 { x: -0.5, y: 0.4 },
-            Vec2 {
-                x: self.players[0].shield as f32 / MAX_SHIELD as f32 * 0.95,
-                y: 0.05,
-            },
-            self.players[0].tint,
-            1,
-        );
-        draw_rect(
-            Vec2 { x: 0.5, y: 0.4 },
-            Vec2 {
-                x: self.players[1].shield as f32 / MAX_SHIELD as f32 * 0.95,
-                y: 0.05,
-            },
-            self.players[1].tint,
-            1,
-        );
-    }
-}
-
-impl Player {
-    fn accelerate(&mut self, speed: f32) {
-        let x_transform = match self.facing {
-            Direction::East => 1.0,
-            Direction::West => -1.0,
-        };
-        if self.loc.y == 0.0 && self.velocity.y == 0.0 {
-            self.velocity.x = speed * x_transform;
-        } else {
-            self.velocity.x += 0.1 * speed * x_transform;
-            self.velocity.y -= 0.002;
-        }
-    }
-
-    fn update_loc(&mut self) {
-        self.loc += self.velocity;
-        self.loc.x = self.loc.x.clamp(-1.0, 1.0);
-        if self.loc.y < 0.0 {
-            // TODO: No landing animation?
-            self.loc.y = 0.0;
-            self.velocity.y = 0.0;
-        }
-    }
-
-    fn center(&self) -> Vec2 {
-        self.loc
-    }
-
-    fn hitbox(&self) -> Option<AABB> {
-        let mut hb = self.animation.sprite().hitbox?;
-        if matches!(self.facing, Direction::West) {
-            // Reflect.
-            (hb.min.x, hb.max.x) = (-hb.max.x, -hb.min.x);
-        }
-        hb.min += self.center();
-        hb.max += self.center();
-        Some(hb)
-    }
-
-    fn hurtbox(&self) -> Option<AABB> {
-        let mut hb = self.animation.sprite().hurtbox?;
-        if matches!(self.facing, Direction::West) {
-            // Reflect.
-            (hb.min.x, hb.max.x) = (-hb.max.x, -hb.min.x);
+        let handles = self.session.local_player_handles();
+        let local_player = (handles.len() == 1).then(|| handles[0]);
+        render::render_state(&self.state, &self.data, local_player);
+
+        if self.opponent_left {
+            draw_text(
+                "Your opponent left. Press Escape to return to the menu.",
+                Vec2 { x: 0., y: -0.35 },
+                RED,
+                TextAlign::Center,
+            );
         }
-        hb.min += self.center();
-        hb.max += self.center();
-        Some(hb)
-    }
-
-    fn render_sprite(&self) {
-        self.animation.render(self.tint, self.center(), self.facing);
-    }
-
-    fn start_attack(&mut self, anims: &Animations) {
-        self.state = PlayerState::Attacking;
-        self.animation = anims["attack"].to_anim();
-    }
-
-    fn start_idle(&mut self) {
-        self.state = PlayerState::Idle;
-    }
 
-    fn ensure_standing(&mut self, anims: &Animations) {
-        if !self.animation.is_instance(&anims["standing"]) {
-            self.animation = anims["standing"].to_anim();
+        if let Some(frame) = self.desync {
+            draw_text(
+                &format!("Desync detected at frame {frame}"),
+                Vec2 { x: 0., y: -0.45 },
+                RED,
+                TextAlign::Center,
+            );
         }
     }
 
-    fn ensure_walking_forwards(&mut self, anims: &Animations) {
-        if !self.animation.is_instance(&anims["forward"]) {
-            self.animation = anims["forward"].to_anim();
+    /// Remembers the first desync for the UI, and dumps our side of it.
+    ///
+    /// We only have the remote checksum, not their state, so the dump has our
+    /// state on the desynced frame and on the previous checked frame (which
+    /// did match), to narrow down what diverged.
+    fn report_desync(&mut self, frame: Frame, local_checksum: u128, remote_checksum: u128) {
+        self.desync.get_or_insert(frame);
+
+        let mut dump = format!(
+            "Desync at frame {frame}\n\
+             local checksum:  {local_checksum:#034x}\n\
+             remote checksum: {remote_checksum:#034x}\n"
+        );
+        for f in [frame - DESYNC_INTERVAL as Frame, frame] {
+            match self.saved_states.get(&f) {
+                Some(state) => {
+                    dump += &format!(
+                        "\nState at frame {f} (checksum {:#034x}):\n{state:#?}\n",
+                        state.checksum()
+                    )
+                }
+                None => dump += &format!("\nState at frame {f} was not kept\n"),
+            }
         }
-    }
 
-    fn ensure_walking_backwards(&mut self, anims: &Animations) {
-        if !self.animation.is_instance(&anims["backward"]) {
-            self.animation = anims["backward"].to_anim();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = format!("desync_frame_{frame}.txt");
+            match std::fs::write(&path, &dump) {
+                Ok(()) => println!("Wrote desync dump to {path}"),
+                Err(e) => println!("Failed to write desync dump to {path}: {e}\n{dump}"),
+            }
         }
+        #[cfg(target_arch = "wasm32")]
+        error!("{dump}");
     }
 
-    fn is_walking_backwards(&self, anims: &Animations) -> bool {
-        self.animation.is_instance(&anims["backward"])
-    }
-
-    fn start_recoil(&mut self, anims: &Animations) {
-        self.state = PlayerState::Recoiling;
-        self.animation = anims["recoil"].to_anim();
-    }
-
-    fn start_block(&mut self, anims: &Animations) {
-        self.state = PlayerState::Blocking;
-        self.animation = anims["block"].to_anim();
-    }
-
-    fn start_death(&mut self, anims: &Animations) {
-        self.state = PlayerState::Death;
-        self.animation = anims["death"].to_anim();
-    }
-
-    fn start_guard_broken(&mut self, anims: &Animations) {
-        self.state = PlayerState::Recoiling;
-        self.animation = anims["guard_broken"].to_anim();
-    }
+    fn advance_frame(&mut self, inputs: Vec<(Input, ggrs::InputStatus)>) {
+        let inputs = [inputs[0].0, inputs[1].0];
+        self.replay.record(self.frame, inputs);
+        self.frame += 1;
 
-    fn handle_hit(&mut self, anims: &Animations) {
-        if self.is_walking_backwards(anims) {
-            if self.shield == 0 {
-                self.start_guard_broken(anims);
-            } else {
-                self.shield -= 1;
-                self.start_block(anims);
-            }
-        } else if self.state == PlayerState::Recoiling {
-            self.start_death(anims);
-        } else {
-            self.shield = self.shield.saturating_sub(1);
-            self.start_recoil(anims);
-        }
+        self.state.advance_frame(inputs, &self.data);
     }
 }
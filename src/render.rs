@@ -0,0 +1,304 @@
+//! Draws the simulated state. Nothing in here feeds back into the simulation.
+
+use comfy::*;
+use fungus_sim::{
+    self as sim, AnnotatedSprite, Combo, CounterHit, Direction, GameData, GameState, Phase, Player,
+    PlayingState, Rules, StockMode,
+};
+
+fn tint(player: usize) -> Color {
+    match player {
+        0 => Color::rgb(1.0, 0.8, 0.8),
+        _ => Color::rgb(0.8, 0.8, 1.0),
+    }
+}
+
+/// The simulation is fixed point; floats only exist from here on.
+fn to_vec2(v: sim::Vec2) -> Vec2 {
+    Vec2 {
+        x: v.x.to_f32(),
+        y: v.y.to_f32(),
+    }
+}
+
+fn to_irect(r: sim::PixelRect) -> IRect {
+    IRect {
+        offset: IVec2 {
+            x: r.offset[0] as i32,
+            y: r.offset[1] as i32,
+        },
+        size: IVec2 {
+            x: r.size[0] as i32,
+            y: r.size[1] as i32,
+        },
+    }
+}
+
+/// `local_player` is the handle of the player on this machine, if there is
+/// exactly one.
+pub fn render_state(state: &GameState, data: &GameData, local_player: Option<usize>) {
+    match &state.phase {
+        Phase::Countdown {
+            frames_left,
+            playing,
+        } => {
+            render_playing(playing, &data.rules);
+            render_stocks(state, data);
+            draw_text(
+                &format!("Round {}", state.round),
+                Vec2 { x: 0., y: 0.2 },
+                BLACK,
+                TextAlign::Center,
+            );
+            draw_text(
+                &frames_left.div_ceil(60).to_string(),
+                Vec2 { x: 0., y: 0.1 },
+                BLACK,
+                TextAlign::Center,
+            );
+        }
+        Phase::Playing(playing) => {
+            render_playing(playing, &data.rules);
+            render_stocks(state, data);
+        }
+        Phase::RoundOver { winner, .. } => {
+            clear_background(WHITE);
+            let msg = match (winner, local_player) {
+                (None, _) => "The round is a draw",
+                (Some(winner), Some(local)) if *winner == local => "You won the round!",
+                (Some(_), Some(_)) => "They won the round",
+                (Some(winner), None) => &format!("Player {} won the round", winner + 1),
+            };
+            draw_text(msg, Vec2::ZERO, BLACK, TextAlign::Center);
+            render_score(state);
+        }
+        Phase::MatchOver {
+            winner,
+            rematch_votes,
+            ..
+        } => {
+            clear_background(WHITE);
+            let msg = match (winner, local_player) {
+                (None, _) => "It's a draw",
+                (Some(winner), Some(local)) if *winner == local => "You won!",
+                (Some(_), Some(_)) => "They won :(",
+                (Some(winner), None) => &format!("Player {} won!", winner + 1),
+            };
+            draw_text(msg, Vec2::ZERO, BLACK, TextAlign::Center);
+            render_score(state);
+
+            let votes = rematch_votes.iter().filter(|&&vote| vote).count();
+            draw_text(
+                &format!("Attack for a rematch ({votes}/2), Escape for the menu"),
+                Vec2 { x: 0., y: -0.2 },
+                BLACK,
+                TextAlign::Center,
+            );
+        }
+    }
+
+    render_wins(state);
+}
+
+fn render_score(state: &GameState) {
+    draw_text(
+        &format!("{} - {}", state.wins[0], state.wins[1]),
+        Vec2 { x: 0., y: -0.1 },
+        BLACK,
+        TextAlign::Center,
+    );
+}
+
+/// A pip per round needed to win, filled in for each round won. Matches on
+/// stocks are a single round, so have none.
+fn render_wins(state: &GameState) {
+    if state.settings.stock_mode == StockMode::PerMatch {
+        return;
+    }
+    for (i, wins) in state.wins.iter().enumerate() {
+        for round in 0..state.settings.first_to {
+            let offset = 0.9 - round as f32 * 0.06;
+            let x = if i == 0 { -offset } else { offset };
+            let color = if round < *wins { tint(i) } else { GRAY };
+            draw_circle(Vec2 { x, y: 0.33 }, 0.02, color, 1);
+        }
+    }
+}
+
+/// A little standing goose per life left, greyed out once lost. Depending on
+/// the stock mode, they're refilled every round or last the whole match.
+/// Not worth showing with a single stock, where the health bar says it all.
+fn render_stocks(state: &GameState, data: &GameData) {
+    if state.settings.stocks <= 1 {
+        return;
+    }
+    let standing = data.animations["standing"].to_anim();
+    for (i, stocks) in state.stocks.iter().enumerate() {
+        for stock in 0..state.settings.stocks {
+            let offset = 0.9 - stock as f32 * 0.06;
+            let x = if i == 0 { -offset } else { offset };
+            let color = if stock < *stocks { tint(i) } else { GRAY };
+            render_sprite(
+                standing.sprite(),
+                color,
+                Vec2 { x, y: 0.28 },
+                0.15,
+                Direction::East,
+            );
+        }
+    }
+}
+
+fn render_playing(state: &PlayingState, rules: &Rules) {
+    clear_background(WHITE);
+
+    for b in state.pushboxes() {
+        draw_rect_outline(to_vec2(b.center()), to_vec2(b.size()), 0.01, BLUE, 1);
+    }
+
+    for b in state.hurtboxes().into_iter().flatten() {
+        draw_rect_outline(to_vec2(b.center()), to_vec2(b.size()), 0.01, DARKGREEN, 1);
+    }
+
+    for b in state.grabboxes().into_iter().flatten() {
+        draw_rect_outline(to_vec2(b.center()), to_vec2(b.size()), 0.01, PURPLE, 2);
+    }
+
+    let projectile_hitboxes = state.projectiles.iter().filter_map(|p| p.hitbox());
+    for b in state
+        .hitboxes()
+        .into_iter()
+        .flatten()
+        .chain(projectile_hitboxes)
+    {
+        // TODO: Not pixel perfect, border extends past hitbox.
+        draw_rect_outline(to_vec2(b.center()), to_vec2(b.size()), 0.01, DARKRED, 2);
+    }
+
+    for p in &state.projectiles {
+        render_sprite(
+            p.animation.sprite(),
+            tint(p.owner),
+            to_vec2(p.loc),
+            1.,
+            p.facing,
+        );
+    }
+
+    for (i, p) in state.players.iter().enumerate() {
+        render_player(p, tint(i));
+
+        let health = p.health as f32 / rules.max_health as f32;
+        render_bar(i, 0.4, 0.04, health, tint(i));
+        let stamina = p.stamina as f32 / rules.max_stamina as f32;
+        render_bar(i, 0.365, 0.025, stamina, ORANGE);
+
+        if let Some(counter) = p.counter_hit {
+            render_callout(1 - i, counter);
+        }
+        render_combo(1 - i, &p.combo);
+    }
+}
+
+/// The combo being dealt by `attacker`, once it's more than one hit. Greyed
+/// out if the defender could have got out of it.
+fn render_combo(attacker: usize, combo: &Combo) {
+    if combo.hits < 2 {
+        return;
+    }
+    let color = if combo.valid { BLACK } else { GRAY };
+    let x = if attacker == 0 { -0.5 } else { 0.5 };
+    draw_text(
+        &format!("{} hits", combo.hits),
+        Vec2 { x, y: 0.14 },
+        color,
+        TextAlign::Center,
+    );
+    draw_text(
+        &format!("{} damage", combo.damage),
+        Vec2 { x, y: 0.08 },
+        color,
+        TextAlign::Center,
+    );
+}
+
+/// Announces a counter-hit on the side of the player who landed it.
+fn render_callout(attacker: usize, counter: CounterHit) {
+    let (msg, color) = match counter {
+        CounterHit::Counter => ("Counter!", ORANGE),
+        CounterHit::Punish => ("Punish!", RED),
+    };
+    let x = if attacker == 0 { -0.5 } else { 0.5 };
+    draw_text(msg, Vec2 { x, y: 0.22 }, color, TextAlign::Center);
+}
+
+/// A gauge on the player's side of the screen, `fraction` full, emptying
+/// towards their edge.
+fn render_bar(player: usize, y: f32, height: f32, fraction: f32, color: Color) {
+    const WIDTH: f32 = 0.95;
+    let side = if player == 0 { -1. } else { 1. };
+    let edge = side * (0.5 + WIDTH / 2.);
+
+    draw_rect(
+        Vec2 { x: side * 0.5, y },
+        Vec2 {
+            x: WIDTH,
+            y: height,
+        },
+        GRAY,
+        0,
+    );
+    let width = fraction * WIDTH;
+    draw_rect(
+        Vec2 {
+            x: edge - side * width / 2.,
+            y,
+        },
+        Vec2 {
+            x: width,
+            y: height,
+        },
+        color,
+        1,
+    );
+}
+
+fn render_player(p: &Player, tint: Color) {
+    // Blink while invulnerable.
+    if p.invulnerable / 4 % 2 == 1 {
+        return;
+    }
+    render_sprite(
+        p.animation.sprite(),
+        tint,
+        to_vec2(p.center()),
+        1.,
+        p.facing,
+    );
+}
+
+/// Flipped horizontally when `facing` is West.
+fn render_sprite(
+    sprite: &AnnotatedSprite,
+    tint: Color,
+    location: Vec2,
+    scale: f32,
+    facing: Direction,
+) {
+    draw_sprite_ex(
+        texture_id(&sprite.texture),
+        location,
+        tint,
+        2,
+        DrawTextureParams {
+            dest_size: Some((to_vec2(sprite.size) * scale).as_world_size()),
+            source_rect: Some(to_irect(sprite.source_rect)),
+            scroll_offset: Vec2::ZERO,
+            rotation: 0.,
+            flip_x: matches!(facing, Direction::West),
+            flip_y: false,
+            pivot: None,
+            blend_mode: BlendMode::Alpha,
+        },
+    );
+}
@@ -0,0 +1,165 @@
+//! Saving, loading and watching replays.
+
+use std::ops::ControlFlow;
+
+use comfy::*;
+use fungus_sim::{GameData, GameState, Replay};
+
+use crate::{render, FPS};
+
+pub const DEFAULT_REPLAY_PATH: &str = "replay.ron";
+
+/// How often to keep a copy of the state while watching, to seek from.
+const SNAPSHOT_INTERVAL: usize = 60;
+
+// TODO: Somewhere to keep replays on the web.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(replay: &Replay, path: &str) {
+    match std::fs::write(path, replay.to_ron()) {
+        Ok(()) => println!("Saved replay to {path}"),
+        Err(e) => println!("Failed to save replay to {path}: {e}"),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(_replay: &Replay, _path: &str) {}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(path: &str, data: &GameData) -> Result<Replay, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    Replay::from_ron(&contents, data).map_err(|e| format!("{path}: {e}"))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(_path: &str, _data: &GameData) -> Result<Replay, String> {
+    Err("Replays are not supported on the web yet".into())
+}
+
+/// Plays a replay back through the simulation, with no network involved.
+pub struct ReplayViewer {
+    replay: Replay,
+    data: GameData,
+
+    /// `snapshots[i]` is the state before frame `i * SNAPSHOT_INTERVAL`.
+    snapshots: Vec<GameState>,
+    state: GameState,
+    /// The next frame to simulate.
+    frame: usize,
+    paused: bool,
+
+    // time variables for tick rate
+    last_update: Instant,
+    accumulator: Duration,
+}
+
+impl ReplayViewer {
+    pub fn new(replay: Replay, data: GameData) -> Self {
+        let state = replay.start_state(&data);
+        ReplayViewer {
+            replay,
+            data,
+            snapshots: vec![state.clone()],
+            state,
+            frame: 0,
+            paused: false,
+            last_update: Instant::now(),
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.replay.inputs.len()
+    }
+
+    fn step(&mut self) {
+        if self.frame >= self.len() {
+            return;
+        }
+
+        self.state
+            .advance_frame(self.replay.inputs[self.frame], &self.data);
+        self.frame += 1;
+
+        if self.frame == self.snapshots.len() * SNAPSHOT_INTERVAL {
+            self.snapshots.push(self.state.clone());
+        }
+    }
+
+    /// Re-simulates from the nearest snapshot at or before `target`, unless
+    /// we are already between that snapshot and `target`.
+    fn seek(&mut self, target: usize) {
+        let target = target.min(self.len());
+        let snapshot = (target / SNAPSHOT_INTERVAL).min(self.snapshots.len() - 1);
+        let snapshot_frame = snapshot * SNAPSHOT_INTERVAL;
+
+        if !(snapshot_frame..=target).contains(&self.frame) {
+            self.state = self.snapshots[snapshot].clone();
+            self.frame = snapshot_frame;
+        }
+        while self.frame < target {
+            self.step();
+        }
+    }
+
+    /// Breaks when the viewer should be closed.
+    pub fn update(&mut self) -> ControlFlow<()> {
+        if is_key_pressed(KeyCode::Escape) {
+            return ControlFlow::Break(());
+        }
+        if is_key_pressed(KeyCode::Space) {
+            self.paused = !self.paused;
+        }
+        if self.paused && is_key_pressed(KeyCode::Right) {
+            self.step();
+        }
+
+        let fps_delta = 1. / FPS;
+        let now = Instant::now();
+        if self.paused {
+            self.accumulator = Duration::ZERO;
+        } else {
+            let delta = now.duration_since(self.last_update);
+            self.accumulator = self.accumulator.saturating_add(delta);
+        }
+        self.last_update = now;
+
+        while self.accumulator.as_secs_f64() > fps_delta {
+            self.accumulator -= Duration::from_secs_f64(fps_delta);
+            self.step();
+        }
+
+        render::render_state(&self.state, &self.data, None);
+
+        let mut close = false;
+        egui::TopBottomPanel::bottom("replay_controls").show(&comfy::egui(), |ui| {
+            ui.horizontal(|ui| {
+                let play_pause = if self.paused { "Play" } else { "Pause" };
+                if ui.button(play_pause).clicked() {
+                    self.paused = !self.paused;
+                }
+                if ui.button("Step").clicked() {
+                    self.paused = true;
+                    self.step();
+                }
+
+                let mut target = self.frame;
+                let len = self.len();
+                let slider = egui::Slider::new(&mut target, 0..=len).text(format!("/ {len}"));
+                if ui.add(slider).changed() {
+                    self.seek(target);
+                }
+
+                if ui.button("Back to Menu").clicked() {
+                    close = true;
+                }
+            });
+            ui.label("Space: pause, Right: step, Escape: back to menu");
+        });
+
+        if close {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}